@@ -1,10 +1,79 @@
-use crate::data::{cache::Cache, history::History};
-use crate::search::{search_query, QueryArgs, QueryResult};
-use crate::ui::{components::*, home, ready, searching, DisplayMode, InputMode};
+use crate::config::Config;
+use crate::crawl::{
+    check::CrawlChecks,
+    reader::{fetch_page, Page},
+};
+use crate::data::{
+    cache::{now_secs, Cache, CacheEntry, CacheHit, CacheMode},
+    cookies::CookieJar,
+    history::History,
+};
+use crate::keymap::{Action, Keymap, Mode};
+use crate::search::{
+    error::Remedy, filters::FilterField, search_query, BackendKind, ParseStrategy, ProxyPool,
+    QueryArgs, QueryResult, ResultsDiff, RetryPolicy, Retrying, SearchBackend, SearchError,
+    SearchFilters, SearchPage, SerpFeatures, Vertical,
+};
+use crate::ui::{components::*, home, reader, ready, searching, DisplayMode, InputMode};
 use crate::utils::StringExt;
 use color_eyre::Result;
 use ratatui::{widgets::ListState, Frame};
-use std::time::Duration;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+pub enum SearchEvent {
+    /// An attempt failed and another one follows after a backoff.
+    Retrying(Retrying),
+    Finished(Result<SearchPage, SearchError>),
+}
+
+/// A search running on a background task.
+pub struct PendingSearch {
+    pub args: QueryArgs,
+    pub backend: BackendKind,
+    /// The last failed attempt, while the search is being retried.
+    pub retrying: Option<Retrying>,
+    rx: mpsc::UnboundedReceiver<SearchEvent>,
+    handle: JoinHandle<()>,
+}
+
+/// A cached page being fetched again in the background.
+pub struct Refresh {
+    pub args: QueryArgs,
+    pub backend: BackendKind,
+    handle: JoinHandle<Result<SearchPage, SearchError>>,
+}
+
+impl Refresh {
+    pub fn key(&self) -> String {
+        self.args.cache_key(self.backend)
+    }
+}
+
+/// A page being fetched for the reader.
+pub struct PendingRead {
+    pub url: String,
+    handle: JoinHandle<Result<Page>>,
+}
+
+/// Expired results on screen while they are searched again.
+pub struct Staleness {
+    /// Cache key of the first page being refreshed.
+    pub key: String,
+    /// How long ago the results on screen were cached.
+    pub age: u64,
+    /// Number of results of the expired page, the ones a refresh replaces.
+    len: usize,
+    pub status: Revalidation,
+}
+
+pub enum Revalidation {
+    Running,
+    Failed(String),
+    /// The fresh results replaced the expired ones.
+    Done(ResultsDiff),
+}
 
 pub struct App {
     pub input: String,
@@ -16,6 +85,10 @@ pub struct App {
     pub has_entered: bool,
     pub is_loading: bool,
     pub error_message: Option<String>,
+    /// Why the last search failed, shown with a hint picked by its kind.
+    pub search_error: Option<SearchError>,
+    /// The query and page last asked for, run again by the retry action.
+    pub last_search: Option<QueryArgs>,
     pub display_mode: DisplayMode,
     pub spinner_frames: Vec<char>,
     pub spinner_index: usize,
@@ -24,12 +97,68 @@ pub struct App {
     pub should_quit: bool,
     pub cache: Cache,
     pub history: History,
+    pub backend_kind: BackendKind,
+    pub backend: Arc<dyn SearchBackend>,
+    /// Shared by every backend so proxy health survives backend switches.
+    pub proxies: Arc<ProxyPool>,
+    pub show_proxies: bool,
+    /// What the cache panel lists, loaded when it opens.
+    pub cache_entries: Vec<CacheEntry>,
+    pub cache_entry_idx: usize,
+    /// The outcome of the last refresh or delete, shown in the cache panel.
+    pub cache_notice: Option<String>,
+    pub refreshes: Vec<Refresh>,
+    /// Set when the results on screen came from an expired cache entry.
+    pub staleness: Option<Staleness>,
+    /// Retries and fallback of the current backend when Google blocks.
+    pub retry_policy: RetryPolicy,
+    pub reader: Option<Page>,
+    pub reader_scroll: u16,
+    pub pending_read: Option<PendingRead>,
+    pub crawl_checks: Vec<CrawlChecks>,
+    pub pending_search: Option<PendingSearch>,
+    /// Query the results on screen belong to.
+    pub current_query: String,
+    pub current_page: usize,
+    pub has_more_results: bool,
+    /// Layout the last scraped page was parsed with, shown in debug mode.
+    pub parse_strategy: Option<ParseStrategy>,
+    /// Answer boxes of the first result page.
+    pub features: SerpFeatures,
+    /// Whether j/k move through the "People also ask" questions instead of
+    /// the results.
+    pub questions_focused: bool,
+    pub question_idx: usize,
+    /// Filters sent with every search.
+    pub filters: SearchFilters,
+    /// Copy of `filters` being edited in the filter panel.
+    pub filter_draft: SearchFilters,
+    pub filter_field: usize,
+    pub vertical: Vertical,
+    pub config: Config,
+    pub keymap: Keymap,
 }
 
 impl App {
-    pub async fn new() -> Result<Self> {
-        let history = History::load().await?;
-        let cache = Cache::load().await?;
+    pub async fn new(config: Config) -> Result<Self> {
+        let mut history = History::load(&config).await?;
+        let mut cache = Cache::load(&config).await?;
+        let backend_kind = config.backend;
+        let cookies = Arc::new(CookieJar::load(&config)?);
+        let warnings: Vec<String> = [
+            &history.take_warnings(),
+            &cache.take_warnings(),
+            &cookies.take_warnings(),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        let proxies = Arc::new(ProxyPool::new(&config, cookies)?);
+        let backend = backend_kind.create(&config, &proxies)?;
+        let retry_policy = RetryPolicy::new(&config, backend_kind, &proxies);
+        let keymap = Keymap::new(&config.keybindings)
+            .map_err(|problems| color_eyre::eyre::eyre!(problems.join("\n")))?;
 
         Ok(Self {
             input: String::new(),
@@ -40,7 +169,9 @@ impl App {
             debug_mode: false,
             has_entered: false,
             is_loading: false,
-            error_message: None,
+            error_message: (!warnings.is_empty()).then(|| warnings.join("\n")),
+            search_error: None,
+            last_search: None,
             display_mode: DisplayMode::Home,
             spinner_frames: vec!['|', '/', '-', '\\'],
             spinner_index: 0,
@@ -49,6 +180,34 @@ impl App {
             should_quit: false,
             cache,
             history,
+            backend_kind,
+            backend,
+            proxies,
+            show_proxies: false,
+            cache_entries: Vec::new(),
+            cache_entry_idx: 0,
+            cache_notice: None,
+            refreshes: Vec::new(),
+            staleness: None,
+            retry_policy,
+            reader: None,
+            reader_scroll: 0,
+            pending_read: None,
+            crawl_checks: Vec::new(),
+            pending_search: None,
+            current_query: String::new(),
+            current_page: 0,
+            has_more_results: true,
+            parse_strategy: None,
+            features: SerpFeatures::default(),
+            questions_focused: false,
+            question_idx: 0,
+            filters: config.filters.clone(),
+            filter_draft: config.filters.clone(),
+            filter_field: 0,
+            vertical: Vertical::default(),
+            config,
+            keymap,
         })
     }
 
@@ -132,41 +291,209 @@ impl App {
             return Ok(());
         }
 
-        self.history.add_query(query.clone()).await;
+        let saved = self.history.add_query(query.clone()).await;
+        let warnings = self.history.take_warnings();
+        self.cancel_search();
 
-        self.display_mode = DisplayMode::Searching;
-        self.is_loading = true;
+        self.current_query = query.clone();
+        self.start_first_page();
 
-        if let Some(cached_results) = self.cache.get(&query).await? {
-            tokio::time::sleep(Duration::from_millis(600)).await;
-            self.messages = cached_results;
+        let args = QueryArgs {
+            query,
+            debug_mode: self.debug_mode,
+            filters: self.filters.clone(),
+            vertical: self.vertical,
+            ..Default::default()
+        };
+        self.last_search = Some(args.clone());
+        if let Some(CacheHit { page, stale_age }) = self
+            .cache
+            .get(&args.cache_key(self.backend_kind), self.cache_mode())
+            .await?
+        {
+            self.start_crawl_checks(&page.results);
+            let len = page.results.len();
+            self.messages = page.results;
+            self.features = page.features;
             self.error_message = None;
+            self.search_error = None;
             self.is_loading = false;
             self.cache.cache_hit = true;
             self.display_mode = DisplayMode::Ready;
-            return Ok(());
+            if let Some(age) = stale_age {
+                self.staleness = Some(Staleness {
+                    key: args.cache_key(self.backend_kind),
+                    age,
+                    len,
+                    status: Revalidation::Running,
+                });
+                self.spawn_refresh(args, self.backend_kind)?;
+            }
+        } else {
+            self.cache.cache_hit = false;
+            self.messages.clear();
+            self.error_message = None;
+            self.search_error = None;
+            self.spawn_search(args);
         }
+        // after the search cleared the last error away
+        self.show_save_result("search history", saved, warnings);
+        Ok(())
+    }
 
-        self.cache.cache_hit = false;
-        self.messages.clear();
+    /// Shows the searching screen with the results of the previous query
+    /// cleared away.
+    fn start_first_page(&mut self) {
+        self.display_mode = DisplayMode::Searching;
+        self.is_loading = true;
+        self.current_page = 0;
+        self.has_more_results = true;
+        self.parse_strategy = None;
+        self.features = SerpFeatures::default();
+        self.questions_focused = false;
+        self.question_idx = 0;
+        self.crawl_checks.clear();
+        self.staleness = None;
+    }
+
+    fn cache_mode(&self) -> CacheMode {
+        self.config.cache_mode.get(self.backend_kind)
+    }
+
+    /// Runs the last search again, page included, without looking in the
+    /// cache. The fresh results replace the cached ones.
+    pub fn retry_search(&mut self) {
+        if self.pending_search.is_some() {
+            return;
+        }
+        let Some(mut args) = self.last_search.clone() else {
+            return;
+        };
+        args.debug_mode = self.debug_mode;
         self.error_message = None;
+        self.search_error = None;
+        self.cache.cache_hit = false;
+        if args.page == 0 {
+            self.start_first_page();
+            self.messages.clear();
+        }
+        self.is_loading = true;
+        self.spawn_search(args);
+    }
 
-        let search_result = search_query(QueryArgs {
-            query: query.clone(),
+    /// Fetches the next result page and appends it to the current results.
+    pub async fn load_more(&mut self) -> Result<()> {
+        if self.current_query.is_empty() || self.pending_search.is_some() || !self.has_more_results
+        {
+            return Ok(());
+        }
+        let args = QueryArgs {
+            query: self.current_query.clone(),
             debug_mode: self.debug_mode,
-        })
-        .await;
+            page: self.current_page + 1,
+            filters: self.filters.clone(),
+            vertical: self.vertical,
+            ..Default::default()
+        };
 
-        match search_result {
-            Ok(results) => {
-                tokio::time::sleep(Duration::from_millis(600)).await;
-                self.messages = results.clone();
-                self.cache.insert(query.clone(), results).await?;
+        self.last_search = Some(args.clone());
+        self.error_message = None;
+        self.search_error = None;
+        if let Some(CacheHit { page, stale_age }) = self
+            .cache
+            .get(&args.cache_key(self.backend_kind), self.cache_mode())
+            .await?
+        {
+            self.cache.cache_hit = true;
+            self.append_results(args.page, page.results);
+            if stale_age.is_some() {
+                // Later pages only get the fresh results into the cache.
+                self.spawn_refresh(args, self.backend_kind)?;
             }
-            Err(e) => {
-                self.error_message = Some(format!("{}", e));
+            return Ok(());
+        }
+
+        self.cache.cache_hit = false;
+        self.is_loading = true;
+        self.spawn_search(args);
+        Ok(())
+    }
+
+    fn spawn_search(&mut self, args: QueryArgs) {
+        let backend = self.backend.clone();
+        let policy = self.retry_policy.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task_args = args.clone();
+        let handle = tokio::spawn(async move {
+            let retry_tx = tx.clone();
+            let on_retry = move |retry| {
+                let _ = retry_tx.send(SearchEvent::Retrying(retry));
+            };
+            let result = search_query(backend.as_ref(), &task_args, &policy, &on_retry).await;
+            let _ = tx.send(SearchEvent::Finished(result));
+        });
+        self.pending_search = Some(PendingSearch {
+            args,
+            backend: self.backend_kind,
+            retrying: None,
+            rx,
+            handle,
+        });
+    }
+
+    /// Adds a further page of results, skipping urls already on screen.
+    fn append_results(&mut self, page: usize, results: Vec<QueryResult>) {
+        let seen: HashSet<String> = self.messages.iter().map(|m| m.url.clone()).collect();
+        let new_results: Vec<QueryResult> = results
+            .into_iter()
+            .filter(|r| !seen.contains(&r.url))
+            .collect();
+        self.current_page = page;
+        self.has_more_results = !new_results.is_empty();
+        self.start_crawl_checks(&new_results);
+        self.messages.extend(new_results);
+    }
+
+    /// Picks up the outcome of the background search, if it has finished.
+    pub async fn poll_search(&mut self) -> Result<()> {
+        let Some(pending) = self.pending_search.as_mut() else {
+            return Ok(());
+        };
+        let event = match pending.rx.try_recv() {
+            Ok(event) => event,
+            Err(mpsc::error::TryRecvError::Empty) => return Ok(()),
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                SearchEvent::Finished(Err(SearchError::Other("Search task stopped".to_string())))
             }
+        };
+        let result = match event {
+            SearchEvent::Retrying(retry) => {
+                pending.retrying = Some(retry);
+                return Ok(());
+            }
+            SearchEvent::Finished(result) => result,
+        };
+        let Some(pending) = self.pending_search.take() else {
+            return Ok(());
+        };
+
+        match result {
+            Ok(page) => {
+                self.parse_strategy = page.strategy;
+                self.cache
+                    .insert(&pending.args, pending.backend, &page)
+                    .await?;
+                if pending.args.page == 0 {
+                    self.start_crawl_checks(&page.results);
+                    self.messages = page.results;
+                    self.features = page.features;
+                } else {
+                    self.append_results(pending.args.page, page.results);
+                }
+            }
+            Err(e) => self.search_error = Some(e),
         }
+        self.save_cookies().await;
 
         self.is_loading = false;
         self.cache.cache_hit = false;
@@ -174,6 +501,265 @@ impl App {
         Ok(())
     }
 
+    /// What to do about a failed search, with the keys to press.
+    fn search_error_hint(&self, err: &SearchError) -> String {
+        let backend_key = self.keymap.key(Mode::Normal, Action::Backend);
+        match err.remedy() {
+            Remedy::Retry => match self.keymap.key(Mode::Normal, Action::Retry) {
+                Some(key) => format!("Press {} to search again.", key),
+                None => "Search again in a moment.".to_string(),
+            },
+            Remedy::CheckConnection => {
+                let proxies_key = self.keymap.key(Mode::Normal, Action::Proxies);
+                match (self.proxies.is_empty(), proxies_key) {
+                    (true, _) => "Check your network connection and try again.".to_string(),
+                    (false, Some(key)) => format!(
+                        "Check your network connection and proxies (press {}) and try again.",
+                        key
+                    ),
+                    (false, None) => {
+                        "Check your network connection and proxies and try again.".to_string()
+                    }
+                }
+            }
+            Remedy::SwitchBackend if matches!(err, SearchError::Blocked { .. }) => {
+                let mut hints = vec!["wait a few minutes".to_string()];
+                if self.proxies.is_empty() {
+                    hints.push("add proxies to the config".to_string());
+                }
+                if let Some(key) = backend_key {
+                    hints.push(format!("press {} to switch backend", key));
+                }
+                format!("Try again later: {}.", hints.join(", "))
+            }
+            Remedy::SwitchBackend => match backend_key {
+                Some(key) => format!("Press {} to switch backend.", key),
+                None => "Try another backend in the config.".to_string(),
+            },
+        }
+    }
+
+    /// Aborts the in-flight search and returns to the search box.
+    pub fn cancel_search(&mut self) {
+        let Some(pending) = self.pending_search.take() else {
+            return;
+        };
+        pending.handle.abort();
+        self.is_loading = false;
+        if pending.args.page > 0 {
+            // keep the pages that already arrived
+            return;
+        }
+        self.has_entered = false;
+        self.display_mode = DisplayMode::Home;
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Per-frame housekeeping: collects finished background work.
+    pub async fn tick(&mut self) -> Result<()> {
+        self.poll_search().await?;
+        self.poll_refreshes().await?;
+        self.poll_read().await;
+        self.poll_crawl_checks();
+        self.show_cache_warnings();
+        Ok(())
+    }
+
+    /// Saves the cookies the searches were answered with.
+    async fn save_cookies(&mut self) {
+        let cookies = self.proxies.cookies();
+        let saved = cookies.save().await;
+        let warnings = cookies.take_warnings();
+        self.show_save_result("cookies", saved, warnings);
+    }
+
+    /// Shows the warnings and the error of saving `what` in the error popup.
+    fn show_save_result(&mut self, what: &str, saved: Result<()>, mut warnings: Vec<String>) {
+        if let Err(e) = saved {
+            warnings.push(format!("Couldn't save the {}: {}", what, e));
+        }
+        if !warnings.is_empty() {
+            self.error_message = Some(warnings.join("\n"));
+        }
+    }
+
+    /// Shows what went wrong with the cache file since it was loaded, in the
+    /// cache panel when it's open.
+    fn show_cache_warnings(&mut self) {
+        let warnings = self.cache.take_warnings();
+        if warnings.is_empty() {
+            return;
+        }
+        let message = warnings.join("\n");
+        if self.input_mode == InputMode::Cache {
+            self.cache_notice = Some(message);
+        } else {
+            self.error_message = Some(message);
+        }
+    }
+
+    /// Re-checks every result that passed the url-only checks; the flags are
+    /// updated in place by [`App::poll_crawl_checks`] as each check finishes.
+    fn start_crawl_checks(&mut self, results: &[QueryResult]) {
+        let urls = results
+            .iter()
+            .filter(|m| m.url_supported_flag)
+            .map(|m| m.url.clone())
+            .collect();
+        self.crawl_checks.push(CrawlChecks::spawn(
+            urls,
+            self.proxies.client(),
+            self.config.timeouts.crawl_check(),
+        ));
+    }
+
+    pub fn poll_crawl_checks(&mut self) {
+        for checks in self.crawl_checks.iter_mut() {
+            while let Some((url, supported)) = checks.try_recv() {
+                for message in self.messages.iter_mut().filter(|m| m.url == url) {
+                    message.url_supported_flag = supported;
+                }
+            }
+        }
+        self.crawl_checks.retain(|checks| !checks.is_done());
+    }
+
+    pub fn is_crawl_check_pending(&self, url: &str) -> bool {
+        self.crawl_checks
+            .iter()
+            .any(|checks| checks.is_pending(url))
+    }
+
+    /// Which keymap the next key press goes through.
+    pub fn key_mode(&self) -> Mode {
+        match self.input_mode {
+            InputMode::Editing => Mode::Editing,
+            InputMode::Filters => Mode::Filters,
+            InputMode::Cache => Mode::Cache,
+            InputMode::Normal => match self.display_mode {
+                DisplayMode::Searching => Mode::Searching,
+                DisplayMode::Reader => Mode::Reader,
+                _ if self.questions_focused => Mode::Questions,
+                _ => Mode::Normal,
+            },
+        }
+    }
+
+    /// Moves j/k between the results and the "People also ask" questions.
+    pub fn toggle_questions_focus(&mut self) {
+        self.questions_focused =
+            !self.questions_focused && !self.features.people_also_ask.is_empty();
+    }
+
+    pub fn next_question(&mut self) {
+        let count = self.features.people_also_ask.len();
+        if count > 0 {
+            self.question_idx = (self.question_idx + 1) % count;
+        }
+    }
+
+    pub fn previous_question(&mut self) {
+        let count = self.features.people_also_ask.len();
+        if count > 0 {
+            self.question_idx = (self.question_idx + count - 1) % count;
+        }
+    }
+
+    /// Runs the selected "People also ask" question as a new search.
+    pub async fn search_question(&mut self) -> Result<()> {
+        let Some(question) = self.features.people_also_ask.get(self.question_idx) else {
+            return Ok(());
+        };
+        self.input = question.clone();
+        self.cursor_idx = self.input.grapheme_len();
+        self.messages.clear();
+        self.error_message = None;
+        self.submit().await?;
+        self.has_entered = true;
+        Ok(())
+    }
+
+    /// Re-runs the current query in another vertical.
+    pub async fn switch_vertical(&mut self, vertical: Vertical) -> Result<()> {
+        self.vertical = vertical;
+        if self.current_query.is_empty() {
+            return Ok(());
+        }
+        self.input = self.current_query.clone();
+        self.cursor_idx = self.input.grapheme_len();
+        self.submit().await
+    }
+
+    pub fn open_filters(&mut self) {
+        self.filter_draft = self.filters.clone();
+        self.filter_field = 0;
+        self.input_mode = InputMode::Filters;
+    }
+
+    /// Closes the filter panel, discarding the draft.
+    pub fn cancel_filters(&mut self) {
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Closes the filter panel and re-runs the current query with the new
+    /// filters, if they changed.
+    pub async fn apply_filters(&mut self) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        if self.filter_draft == self.filters {
+            return Ok(());
+        }
+        self.filters = self.filter_draft.clone();
+        if self.current_query.is_empty() || self.display_mode == DisplayMode::Home {
+            return Ok(());
+        }
+        self.input = self.current_query.clone();
+        self.cursor_idx = self.input.grapheme_len();
+        self.submit().await
+    }
+
+    pub fn select_filter(&mut self, delta: isize) {
+        let count = FilterField::ALL.len() as isize;
+        self.filter_field = (self.filter_field as isize + delta).rem_euclid(count) as usize;
+    }
+
+    fn selected_filter(&self) -> FilterField {
+        FilterField::ALL[self.filter_field]
+    }
+
+    /// Types into a text filter, or cycles a list filter on space.
+    pub fn filter_insert_char(&mut self, c: char) {
+        let field = self.selected_filter();
+        match self.filter_draft.text_mut(field) {
+            Some(text) => text.push(c),
+            None if c == ' ' => self.filter_draft.cycle(field),
+            None => {}
+        }
+    }
+
+    pub fn filter_delete_char(&mut self) {
+        if let Some(text) = self.filter_draft.text_mut(self.selected_filter()) {
+            text.pop();
+        }
+    }
+
+    pub fn cycle_filter(&mut self) {
+        self.filter_draft.cycle(self.selected_filter());
+    }
+
+    pub fn reset_filter(&mut self) {
+        let field = self.selected_filter();
+        let default = SearchFilters::default();
+        match field {
+            FilterField::TimeRange => self.filter_draft.time_range = default.time_range,
+            FilterField::SafeSearch => self.filter_draft.safe_search = default.safe_search,
+            _ => {
+                if let Some(text) = self.filter_draft.text_mut(field) {
+                    text.clear();
+                }
+            }
+        }
+    }
+
     pub fn next_history(&mut self) {
         self.history.next();
         self.history_list_state.select(Some(self.history.index));
@@ -194,7 +780,10 @@ impl App {
         match self.display_mode {
             DisplayMode::Home => {
                 home::render(self, frame);
-                if self.history.show_history_popup {
+                if let Some(err_msg) = &self.error_message {
+                    let popup = create_error_popup(err_msg);
+                    frame.render_widget(popup.clone(), popup.calculate_area(screen));
+                } else if self.history.show_history_popup {
                     let popup = create_history_popup(
                         self.history.get_queries(),
                         self.history.index,
@@ -209,9 +798,22 @@ impl App {
                 }
                 searching::render(self, frame)
             }
+            DisplayMode::Reader => {
+                if self.pending_read.is_some() {
+                    self.spinner_index = (self.spinner_index + 1) % self.spinner_frames.len();
+                }
+                reader::render(self, frame);
+                if let Some(err_msg) = &self.error_message {
+                    let popup = create_error_popup(err_msg);
+                    frame.render_widget(popup.clone(), popup.calculate_area(screen));
+                }
+            }
             DisplayMode::Ready => {
                 ready::render(self, frame);
-                if let Some(err_msg) = &self.error_message {
+                if let Some(err) = &self.search_error {
+                    let popup = create_search_error_popup(err, self.search_error_hint(err));
+                    frame.render_widget(popup.clone(), popup.calculate_area(screen));
+                } else if let Some(err_msg) = &self.error_message {
                     let popup = create_error_popup(err_msg);
                     frame.render_widget(popup.clone(), popup.calculate_area(screen));
                 } else if self.messages.is_empty() && self.has_entered && !self.is_loading {
@@ -225,12 +827,36 @@ impl App {
                 }
             }
         }
+
+        if self.input_mode == InputMode::Filters {
+            let popup = create_filter_popup(&self.filter_draft, self.filter_field);
+            frame.render_widget(popup.clone(), popup.calculate_area(screen));
+        } else if self.input_mode == InputMode::Cache {
+            let refreshing: Vec<String> =
+                self.refreshes.iter().map(|refresh| refresh.key()).collect();
+            let popup = create_cache_panel(
+                &self.cache_entries,
+                self.cache_entry_idx,
+                &refreshing,
+                self.cache_notice.as_deref(),
+                now_secs().unwrap_or_default(),
+                screen.height as usize,
+                help_keys(&self.keymap, Mode::Cache),
+            );
+            frame.render_widget(popup.clone(), popup.calculate_area(screen));
+        } else if self.show_proxies {
+            let popup = create_proxy_popup(&self.proxies.status());
+            frame.render_widget(popup.clone(), popup.calculate_area(screen));
+        }
     }
 
     pub fn clear_input(&mut self) {
+        self.crawl_checks.clear();
+        self.questions_focused = false;
         self.input.clear();
         self.cursor_idx = 0;
         self.cache.cache_hit = false;
+        self.staleness = None;
         self.has_entered = false;
         self.display_mode = DisplayMode::Home;
         self.input_mode = InputMode::Editing;
@@ -244,10 +870,268 @@ impl App {
         }
     }
 
+    /// Opens the reader on the selected result, fetching the page in the
+    /// background while it shows a spinner.
+    pub fn open_reader(&mut self) {
+        let Some(message) = self.messages.get(self.selected_idx) else {
+            return;
+        };
+        if !message.url_supported_flag {
+            self.error_message = Some(format!("Crawl not supported for {}", message.url));
+            return;
+        }
+        let client = self.proxies.client();
+        let url = message.url.clone();
+        let timeout = self.config.timeouts.reader();
+        let task_url = url.clone();
+        let handle = tokio::spawn(async move { fetch_page(&client, &task_url, timeout).await });
+        self.pending_read = Some(PendingRead { url, handle });
+        self.reader = None;
+        self.reader_scroll = 0;
+        self.error_message = None;
+        self.display_mode = DisplayMode::Reader;
+    }
+
+    /// Shows the page for the reader once it's fetched, or goes back to the
+    /// results with the error.
+    async fn poll_read(&mut self) {
+        if !self
+            .pending_read
+            .as_ref()
+            .is_some_and(|pending| pending.handle.is_finished())
+        {
+            return;
+        }
+        let Some(pending) = self.pending_read.take() else {
+            return;
+        };
+        match pending.handle.await.unwrap_or_else(|e| Err(e.into())) {
+            Ok(page) => self.reader = Some(page),
+            Err(e) => {
+                self.error_message = Some(format!("Error reading page: {}", e));
+                self.display_mode = DisplayMode::Ready;
+            }
+        }
+    }
+
+    /// Leaves the reader, stopping the fetch if the page is still loading.
+    pub fn close_reader(&mut self) {
+        if let Some(pending) = self.pending_read.take() {
+            pending.handle.abort();
+        }
+        self.reader = None;
+        self.reader_scroll = 0;
+        self.error_message = None;
+        self.display_mode = DisplayMode::Ready;
+    }
+
+    pub fn scroll_reader(&mut self, delta: i32) {
+        self.reader_scroll = self.reader_scroll.saturating_add_signed(delta as i16);
+    }
+
+    pub fn open_reader_url(&mut self) {
+        if let Some(page) = &self.reader {
+            if let Err(e) = open::that(&page.url) {
+                self.error_message = Some(format!("Error opening URL: {}", e));
+            }
+        }
+    }
+
     pub fn toggle_debug_mode(&mut self) {
         self.debug_mode = !self.debug_mode;
     }
 
+    pub fn cycle_backend(&mut self) {
+        let next = self.backend_kind.next();
+        match next.create(&self.config, &self.proxies) {
+            Ok(backend) => {
+                self.backend_kind = next;
+                self.backend = backend;
+                self.retry_policy = RetryPolicy::new(&self.config, next, &self.proxies);
+                self.error_message = None;
+            }
+            Err(e) => self.error_message = Some(format!("{}", e)),
+        }
+    }
+
+    pub fn toggle_proxy_status(&mut self) {
+        self.show_proxies = !self.show_proxies;
+    }
+
+    pub fn open_cache_panel(&mut self) -> Result<()> {
+        self.cache_entries = self.cache.entries()?;
+        self.cache_entry_idx = 0;
+        self.cache_notice = None;
+        self.input_mode = InputMode::Cache;
+        Ok(())
+    }
+
+    pub fn close_cache_panel(&mut self) {
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn select_cache_entry(&mut self, delta: isize) {
+        let count = self.cache_entries.len() as isize;
+        if count > 0 {
+            self.cache_entry_idx =
+                (self.cache_entry_idx as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Lists the cache again, keeping the same entry selected if it's
+    /// still there.
+    fn reload_cache_entries(&mut self) -> Result<()> {
+        let selected = self
+            .cache_entries
+            .get(self.cache_entry_idx)
+            .map(|entry| entry.key.clone());
+        self.cache_entries = self.cache.entries()?;
+        self.cache_entry_idx = selected
+            .and_then(|key| self.cache_entries.iter().position(|e| e.key == key))
+            .unwrap_or(self.cache_entry_idx)
+            .min(self.cache_entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    pub fn delete_cache_entry(&mut self) -> Result<()> {
+        let Some(entry) = self.cache_entries.get(self.cache_entry_idx) else {
+            return Ok(());
+        };
+        self.cache.remove(&entry.key)?;
+        self.cache_notice = Some(format!("Deleted '{}'", entry.query));
+        self.cache_entries.remove(self.cache_entry_idx);
+        self.reload_cache_entries()
+    }
+
+    /// Searches the selected entry again in the background and replaces it
+    /// with the fresh results.
+    pub fn refresh_cache_entry(&mut self) {
+        let Some(entry) = self.cache_entries.get(self.cache_entry_idx) else {
+            return;
+        };
+        let Some(args) = entry.search.clone() else {
+            self.cache_notice = Some(format!(
+                "'{}' was cached without its filters; search it again to refresh it",
+                entry.query
+            ));
+            return;
+        };
+        self.cache_notice = None;
+        let (query, backend) = (entry.query.clone(), entry.backend);
+        if let Err(e) = self.spawn_refresh(args, backend) {
+            self.cache_notice = Some(format!("Couldn't refresh '{}': {}", query, e));
+        }
+    }
+
+    /// Searches `args` again with `kind` in the background unless it
+    /// already is.
+    fn spawn_refresh(&mut self, args: QueryArgs, kind: BackendKind) -> Result<()> {
+        let key = args.cache_key(kind);
+        if self.refreshes.iter().any(|r| r.key() == key) {
+            return Ok(());
+        }
+        let (backend, policy) = if kind == self.backend_kind {
+            (self.backend.clone(), self.retry_policy.clone())
+        } else {
+            (
+                kind.create(&self.config, &self.proxies)?,
+                RetryPolicy::new(&self.config, kind, &self.proxies),
+            )
+        };
+        let task_args = args.clone();
+        let handle = tokio::spawn(async move {
+            search_query(backend.as_ref(), &task_args, &policy, &|_| {}).await
+        });
+        self.refreshes.push(Refresh {
+            args,
+            backend: kind,
+            handle,
+        });
+        Ok(())
+    }
+
+    /// Stores the pages of finished refreshes, and puts the fresh results
+    /// on screen in place of expired ones.
+    pub async fn poll_refreshes(&mut self) -> Result<()> {
+        if !self.refreshes.iter().any(|r| r.handle.is_finished()) {
+            return Ok(());
+        }
+        let (done, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.refreshes)
+            .into_iter()
+            .partition(|r| r.handle.is_finished());
+        self.refreshes = running;
+        self.save_cookies().await;
+        for refresh in done {
+            let stale = self
+                .staleness
+                .as_ref()
+                .is_some_and(|s| s.key == refresh.key());
+            let result = refresh
+                .handle
+                .await
+                .unwrap_or_else(|e| Err(SearchError::Other(e.to_string())));
+            self.cache_notice = Some(match result {
+                Ok(page) => {
+                    self.cache
+                        .insert(&refresh.args, refresh.backend, &page)
+                        .await?;
+                    if stale {
+                        self.replace_stale_results(page);
+                    }
+                    format!("Refreshed '{}'", refresh.args.query)
+                }
+                Err(e) => {
+                    if let Some(staleness) = self.staleness.as_mut().filter(|_| stale) {
+                        staleness.status = Revalidation::Failed(e.to_string());
+                    }
+                    format!("Couldn't refresh '{}': {}", refresh.args.query, e)
+                }
+            });
+        }
+        if self.input_mode == InputMode::Cache {
+            self.reload_cache_entries()?;
+        }
+        Ok(())
+    }
+
+    /// Swaps the expired first page for its fresh results, keeping the
+    /// selected result selected if it's still there. Results of pages
+    /// loaded since stay after it unless the fresh page has them too.
+    fn replace_stale_results(&mut self, page: SearchPage) {
+        let Some(staleness) = &self.staleness else {
+            return;
+        };
+        let stale_len = staleness.len.min(self.messages.len());
+        let diff = ResultsDiff::between(&self.messages[..stale_len], &page.results);
+        let selected = self.messages.get(self.selected_idx).map(|m| m.url.clone());
+        let added: Vec<QueryResult> = page
+            .results
+            .iter()
+            .filter(|r| diff.added.contains(&r.url))
+            .cloned()
+            .collect();
+        self.start_crawl_checks(&added);
+        let fresh: HashSet<&str> = page.results.iter().map(|r| r.url.as_str()).collect();
+        let later: Vec<QueryResult> = self.messages[stale_len..]
+            .iter()
+            .filter(|m| !fresh.contains(m.url.as_str()))
+            .cloned()
+            .collect();
+        self.messages = page.results;
+        self.messages.extend(later);
+        self.features = page.features;
+        self.cache.cache_hit = false;
+        let idx = selected
+            .and_then(|url| self.messages.iter().position(|m| m.url == url))
+            .unwrap_or(0);
+        self.selected_idx = idx;
+        self.results_list_state
+            .select((!self.messages.is_empty()).then_some(idx));
+        if let Some(staleness) = &mut self.staleness {
+            staleness.status = Revalidation::Done(diff);
+        }
+    }
+
     pub fn toggle_cache_notification(&mut self) {
         self.cache.enable_cache_hit_notification = !self.cache.enable_cache_hit_notification;
     }
@@ -0,0 +1,583 @@
+use crate::config::{Config, ConfigError};
+use crate::data::cache::{now_secs, Cache, CacheEntry, Stop, FORMAT_VERSION};
+use crate::data::cookies::CookieJar;
+use crate::search::{
+    filters::{SafeSearch, TimeRange},
+    search_query, BackendKind, ProxyPool, QueryArgs, QueryResult, RetryPolicy, Retrying,
+    SearchError, SearchFilters, Vertical, RESULTS_PER_PAGE,
+};
+use crate::utils::{format_age, format_size};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use color_eyre::Result;
+use std::{path::PathBuf, process::ExitCode, sync::Arc, time::Duration};
+use tokio::time::Instant;
+
+pub const EXIT_NO_RESULTS: u8 = 2;
+pub const EXIT_NETWORK_ERROR: u8 = 3;
+pub const EXIT_PARSE_ERROR: u8 = 4;
+pub const EXIT_BLOCKED: u8 = 5;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "CmdGoogle",
+    version,
+    about = "Google search from your terminal"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Config file to use instead of the default one
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Search backend, overrides `backend`
+    #[arg(long, global = true, value_enum)]
+    pub backend: Option<BackendKind>,
+    /// Search request timeout in seconds, overrides `timeouts.search_secs`
+    #[arg(long, global = true, value_name = "SECS")]
+    pub timeout: Option<u64>,
+    /// How long cached results are reused, overrides `cache_ttl_secs`
+    #[arg(long, global = true, value_name = "SECS")]
+    pub cache_ttl: Option<u64>,
+    /// Proxy for the scraper, may be repeated; replaces `proxies`
+    #[arg(long = "proxy", global = true, value_name = "URL")]
+    pub proxies: Vec<String>,
+}
+
+impl Cli {
+    /// Loads the config file and applies the command line overrides on top.
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if let Some(backend) = self.backend {
+            config.backend = backend;
+        }
+        if let Some(secs) = self.timeout {
+            config.timeouts.search_secs = secs;
+        }
+        if let Some(secs) = self.cache_ttl {
+            config.cache_ttl_secs = secs;
+        }
+        if !self.proxies.is_empty() {
+            config.proxies = self.proxies.clone();
+        }
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError::Overrides(problems));
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a search and print the results to stdout without starting the TUI.
+    ///
+    /// Exit codes: 0 success, 1 other error, 2 no results, 3 network error,
+    /// 4 parse failure, 5 blocked by Google.
+    Search(SearchArgs),
+    /// Inspect or maintain the search cache.
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// List the cached pages, newest first.
+    List,
+    /// Print the cached results of a query, every cached page of it.
+    Show {
+        query: String,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Delete every cached page, or only the old ones.
+    Purge {
+        /// Only delete pages stored longer ago than this, e.g. 90s, 30m, 12h
+        /// or 7d
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+    },
+    /// Show how many pages are cached and how much space they take.
+    Stats,
+    /// Upgrade the cache to the current format now rather than on the next
+    /// start, importing a cache left by older versions.
+    ///
+    /// Fails while another instance has a cache in an older format open.
+    Migrate,
+    /// Check that every cached page can be read, without changing anything.
+    ///
+    /// Exits with 1 if a problem was found.
+    Verify,
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// The search query
+    pub query: String,
+    /// Maximum number of results to print
+    #[arg(long, default_value_t = 10)]
+    pub limit: usize,
+    /// Result page to fetch, starting at 1
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page: u32,
+    /// Results requested per page
+    #[arg(long, default_value_t = RESULTS_PER_PAGE as u32, value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub num: u32,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Which kind of results to search for
+    #[arg(long, value_enum, default_value_t = Vertical::Web)]
+    pub vertical: Vertical,
+    #[command(flatten)]
+    pub filters: FilterArgs,
+    /// Save the raw HTML to debug_raw.html and print timings to stderr
+    #[arg(long)]
+    pub debug: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct FilterArgs {
+    /// Only return results from this domain (site:)
+    #[arg(long)]
+    pub site: Option<String>,
+    /// Only return documents of this type, e.g. pdf (filetype:)
+    #[arg(long)]
+    pub filetype: Option<String>,
+    /// Only return results from this period
+    #[arg(long, value_enum)]
+    pub time: Option<TimeRange>,
+    /// Country to search from, e.g. us (gl)
+    #[arg(long)]
+    pub region: Option<String>,
+    /// Interface language, e.g. en (hl)
+    #[arg(long)]
+    pub lang: Option<String>,
+    /// Safe search level
+    #[arg(long, value_enum)]
+    pub safe: Option<SafeSearch>,
+}
+
+impl FilterArgs {
+    /// `filters` with every option given on the command line replaced.
+    fn apply(self, mut filters: SearchFilters) -> SearchFilters {
+        if let Some(site) = self.site {
+            filters.site = site;
+        }
+        if let Some(filetype) = self.filetype {
+            filters.filetype = filetype;
+        }
+        if let Some(time) = self.time {
+            filters.time_range = time;
+        }
+        if let Some(region) = self.region {
+            filters.region = region;
+        }
+        if let Some(lang) = self.lang {
+            filters.language = lang;
+        }
+        if let Some(safe) = self.safe {
+            filters.safe_search = safe;
+        }
+        filters
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Tsv,
+}
+
+pub async fn run_search(args: SearchArgs, config: &Config) -> Result<ExitCode> {
+    let cookies = Arc::new(CookieJar::load(config)?);
+    for warning in cookies.take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    let proxies = Arc::new(ProxyPool::new(config, cookies)?);
+    let backend = config.backend.create(config, &proxies)?;
+    let policy = RetryPolicy::new(config, config.backend, &proxies);
+    let query = QueryArgs {
+        query: args.query.trim().to_string(),
+        debug_mode: args.debug,
+        page: args.page as usize - 1,
+        num: args.num as usize,
+        filters: args.filters.apply(config.filters.clone()),
+        vertical: args.vertical,
+    };
+
+    let start = Instant::now();
+    let debug = args.debug;
+    let on_retry = move |retry: Retrying| {
+        if debug {
+            eprintln!("{}; retrying (attempt {})", retry.reason, retry.attempt);
+        }
+    };
+    let result = search_query(backend.as_ref(), &query, &policy, &on_retry).await;
+    if let Err(e) = proxies.cookies().save().await {
+        eprintln!("Warning: couldn't save the cookies: {}", e);
+    }
+    for warning in proxies.cookies().take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if args.debug {
+        eprintln!("Search query took {:?}", start.elapsed());
+        print_proxy_status(&proxies);
+    }
+    let page = match result {
+        Ok(page) => page,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return Ok(ExitCode::from(exit_code_for(&e)));
+        }
+    };
+    if let (true, Some(strategy)) = (args.debug, page.strategy) {
+        eprintln!("Parsed with the {} strategy", strategy);
+    }
+
+    let results = page.results;
+    if results.is_empty() {
+        eprintln!("No search result found for '{}'", query.query);
+        return Ok(ExitCode::from(EXIT_NO_RESULTS));
+    }
+
+    let results = &results[..results.len().min(args.limit)];
+    print!("{}", format_results(results, args.format)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+pub async fn run_cache(command: CacheCommand, config: &Config) -> Result<ExitCode> {
+    match command {
+        CacheCommand::List => list_cache(config).await,
+        CacheCommand::Show { query, format } => show_cached(config, &query, format).await,
+        CacheCommand::Purge { older_than } => purge_cache(config, older_than).await,
+        CacheCommand::Stats => cache_stats(config).await,
+        CacheCommand::Migrate => migrate_cache(config).await,
+        CacheCommand::Verify => verify_cache(config).await,
+    }
+}
+
+/// Opens the cache, printing what went wrong loading it, or returns `None`
+/// when it can't be used.
+async fn load_cache(config: &Config) -> Result<Option<Cache>> {
+    let mut cache = Cache::load(config).await?;
+    for warning in cache.take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(cache.is_enabled().then_some(cache))
+}
+
+async fn list_cache(config: &Config) -> Result<ExitCode> {
+    let Some(mut cache) = load_cache(config).await? else {
+        return Ok(ExitCode::from(1));
+    };
+    let now = now_secs()?;
+    for entry in cache.entries()? {
+        println!("{}", entry.describe(now));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn show_cached(config: &Config, query: &str, format: OutputFormat) -> Result<ExitCode> {
+    let Some(mut cache) = load_cache(config).await? else {
+        return Ok(ExitCode::from(1));
+    };
+    let now = now_secs()?;
+    let query = query.trim().to_lowercase();
+    let mut entries: Vec<CacheEntry> = cache
+        .entries()?
+        .into_iter()
+        .filter(|entry| entry.query == query)
+        .collect();
+    if entries.is_empty() {
+        eprintln!("Nothing cached for '{}'", query);
+        return Ok(ExitCode::from(EXIT_NO_RESULTS));
+    }
+    entries.sort_by(|a, b| a.params.cmp(&b.params));
+    for entry in entries {
+        let Some(page) = cache.peek(&entry.key)? else {
+            eprintln!("The cached page {} is unreadable", entry.key);
+            continue;
+        };
+        if let OutputFormat::Text = format {
+            println!("# {}\n", entry.describe(now));
+        }
+        print!("{}", format_results(&page.results, format)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn purge_cache(config: &Config, older_than: Option<Duration>) -> Result<ExitCode> {
+    let Some(mut cache) = load_cache(config).await? else {
+        return Ok(ExitCode::from(1));
+    };
+    let removed = cache.purge(older_than)?;
+    for warning in cache.take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    println!(
+        "Removed {} cached {}",
+        removed,
+        if removed == 1 { "page" } else { "pages" }
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn cache_stats(config: &Config) -> Result<ExitCode> {
+    let Some(mut cache) = load_cache(config).await? else {
+        return Ok(ExitCode::from(1));
+    };
+    let now = now_secs()?;
+    let stats = cache.stats()?;
+    println!("{}", stats.path.display());
+    println!("{} pages, {} expired", stats.entries, stats.expired);
+    let cap = match stats.max_bytes {
+        0 => "no limit".to_string(),
+        max => format!("limit {}", format_size(max)),
+    };
+    println!(
+        "{} of results in a {} file ({})",
+        format_size(stats.payload_len),
+        format_size(stats.file_len),
+        cap
+    );
+    if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+        println!(
+            "Oldest stored {} ago, newest {} ago",
+            format_age(now.saturating_sub(oldest)),
+            format_age(now.saturating_sub(newest))
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn migrate_cache(config: &Config) -> Result<ExitCode> {
+    let Some(cache) = load_cache(config).await? else {
+        return Ok(ExitCode::from(1));
+    };
+    for upgrade in &cache.upgrades {
+        println!("{}", upgrade);
+    }
+    if cache.upgrades.is_empty() {
+        println!("The search cache is up to date (format {})", FORMAT_VERSION);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parses `--older-than`: a number of seconds, or a number followed by
+/// s, m, h, d or w.
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' isn't an age like 30m, 12h or 7d", value))?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 604800,
+        _ => return Err(format!("unknown unit '{}', use s, m, h, d or w", unit)),
+    };
+    Ok(Duration::from_secs(number.saturating_mul(unit)))
+}
+
+async fn verify_cache(config: &Config) -> Result<ExitCode> {
+    let status = Cache::verify(config).await?;
+    let mut problems = Vec::new();
+    match &status.store {
+        None => println!("No search cache at {}", status.path.display()),
+        Some(store) if store.version > FORMAT_VERSION => problems.push(format!(
+            "{} is in format {}, written by a newer version; this one reads up to {}",
+            status.path.display(),
+            store.version,
+            FORMAT_VERSION
+        )),
+        Some(store) => {
+            println!("{}", status.path.display());
+            if store.version < FORMAT_VERSION {
+                println!(
+                    "Format {}, upgraded to {} on the next start or by `cache migrate`",
+                    store.version, FORMAT_VERSION
+                );
+            } else {
+                println!("Format {}", store.version);
+            }
+            println!(
+                "{} entries in {} bytes, {} of them superseded",
+                store.entries, store.len, store.superseded
+            );
+            match store.stop {
+                Stop::End => {}
+                Stop::Torn => problems.push(format!(
+                    "The record at byte {} is cut short and will be dropped",
+                    store.stopped_at
+                )),
+                Stop::Corrupt => problems.push(format!(
+                    "The record at byte {} is unreadable; it and everything after it will be dropped",
+                    store.stopped_at
+                )),
+            }
+            for key in &store.unreadable {
+                problems.push(format!("The entry for {:?} is unreadable", key));
+            }
+        }
+    }
+    match &status.legacy {
+        Some((path, Some(entries))) => println!(
+            "{} entries of an older cache in {} will be imported on the next start",
+            entries,
+            path.display()
+        ),
+        Some((path, None)) => problems.push(format!("{} is unreadable", path.display())),
+        None => {}
+    }
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(ExitCode::SUCCESS);
+    }
+    for problem in &problems {
+        println!("Problem: {}", problem);
+    }
+    Ok(ExitCode::from(1))
+}
+
+fn print_proxy_status(proxies: &ProxyPool) {
+    for proxy in proxies.status() {
+        eprintln!(
+            "Proxy {}: {} ok, {} failed{}",
+            proxy.url,
+            proxy.successes,
+            proxy.failures,
+            proxy
+                .last_error
+                .map(|e| format!(" ({})", e))
+                .unwrap_or_default()
+        );
+    }
+}
+
+fn format_results(results: &[QueryResult], format: OutputFormat) -> Result<String> {
+    let mut out = String::new();
+    match format {
+        OutputFormat::Json => {
+            out.push_str(&serde_json::to_string_pretty(results)?);
+            out.push('\n');
+        }
+        OutputFormat::Text => {
+            for (i, result) in results.iter().enumerate() {
+                out.push_str(&format!("{}. {}\n", i + 1, result.title));
+                out.push_str(&format!("   {}\n", result.url));
+                if !result.description.is_empty() {
+                    out.push_str(&format!("   {}\n", result.description));
+                }
+                let details = result.details();
+                if !details.is_empty() {
+                    out.push_str(&format!("   {}\n", details.join(" · ")));
+                }
+                out.push('\n');
+            }
+        }
+        OutputFormat::Tsv => {
+            for result in results {
+                out.push_str(&format!(
+                    "{}\t{}\t{}\n",
+                    tsv_field(&result.url),
+                    tsv_field(&result.title),
+                    tsv_field(&result.description)
+                ));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn exit_code_for(err: &SearchError) -> u8 {
+    match err {
+        SearchError::Blocked { .. } => EXIT_BLOCKED,
+        SearchError::Timeout
+        | SearchError::Offline(_)
+        | SearchError::Http { .. }
+        | SearchError::RateLimited(_) => EXIT_NETWORK_ERROR,
+        SearchError::Parse(_) => EXIT_PARSE_ERROR,
+        SearchError::Unsupported(_) | SearchError::Other(_) => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Blocked;
+
+    #[test]
+    fn maps_search_errors_to_exit_codes() {
+        let blocked = Blocked {
+            captcha: true,
+            retry_after: None,
+            attempts: 3,
+        };
+        let cases = [
+            (SearchError::Timeout, EXIT_NETWORK_ERROR),
+            (
+                SearchError::Offline("google.com".into()),
+                EXIT_NETWORK_ERROR,
+            ),
+            (
+                SearchError::Http {
+                    status: 503,
+                    message: String::new(),
+                },
+                EXIT_NETWORK_ERROR,
+            ),
+            (SearchError::RateLimited("quota".into()), EXIT_NETWORK_ERROR),
+            (
+                SearchError::Parse("no results block".into()),
+                EXIT_PARSE_ERROR,
+            ),
+            (
+                SearchError::Blocked {
+                    blocked,
+                    fallback: None,
+                },
+                EXIT_BLOCKED,
+            ),
+            (SearchError::Unsupported("no API key".into()), 1),
+            (SearchError::Other("disk full".into()), 1),
+        ];
+        for (err, code) in cases {
+            assert_eq!(exit_code_for(&err), code, "{:?}", err);
+        }
+        // the codes are a documented contract
+        assert_eq!(
+            [
+                EXIT_NO_RESULTS,
+                EXIT_NETWORK_ERROR,
+                EXIT_PARSE_ERROR,
+                EXIT_BLOCKED
+            ],
+            [2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_age("2d"), Ok(Duration::from_secs(2 * 86400)));
+        assert_eq!(parse_age(" 90 "), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("1w"), Ok(Duration::from_secs(604800)));
+        assert!(parse_age("").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("-5m").is_err());
+        assert!(parse_age("3y").unwrap_err().contains("unknown unit 'y'"));
+        assert!(parse_age("1.5h").is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use crate::data::cache::CacheMode;
+use crate::keymap::{Keymap, KeymapConfig};
+use crate::search::{proxy::ProxySelection, BackendKind, SearchFilters};
+use ratatui::style::Color;
+use reqwest::Url;
+use serde::Deserializer;
+use serde_derive::Deserialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+/// Directory name used under the platform config, cache and data dirs.
+pub const APP_DIR: &str = "terminal_google_search";
+
+/// Settings read from `config.toml`. Every field is optional in the file;
+/// missing ones keep their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub backend: BackendKind,
+    /// How long cached results are served before searching again.
+    pub cache_ttl_secs: u64,
+    /// Size the cached results may grow to before the least recently used
+    /// are evicted; 0 never evicts.
+    pub cache_max_mb: u64,
+    pub cache_hit_notification: bool,
+    /// Whether expired results are shown while searching again, per backend.
+    pub cache_mode: CacheModes,
+    /// Where the result cache lives, defaults to the platform cache dir.
+    pub cache_dir: Option<PathBuf>,
+    /// Where the search history lives, defaults to the platform data dir.
+    pub data_dir: Option<PathBuf>,
+    pub timeouts: Timeouts,
+    /// Browser user agents the scraper picks from for every request.
+    pub user_agents: Vec<String>,
+    /// `http://`, `https://` or `socks5://` proxies for the scraper.
+    pub proxies: Vec<String>,
+    pub proxy_selection: ProxySelection,
+    /// How long a blocked, timed out or unreachable proxy is skipped; 0
+    /// keeps every proxy in rotation.
+    pub proxy_quarantine_secs: u64,
+    /// Retries after a timeout or a 5xx answer.
+    pub retry: Retry,
+    /// What to do when Google answers with its CAPTCHA page or a 429.
+    pub block_retry: BlockRetry,
+    pub custom_search: CustomSearchConfig,
+    pub theme: Theme,
+    /// Keys per mode, see `keymap::Mode`.
+    pub keybindings: KeymapConfig,
+    /// Filters every search starts with.
+    pub filters: SearchFilters,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            cache_ttl_secs: 60 * 60 * 24,
+            cache_max_mb: 64,
+            cache_hit_notification: true,
+            cache_mode: CacheModes::default(),
+            cache_dir: None,
+            data_dir: None,
+            timeouts: Timeouts::default(),
+            user_agents: DEFAULT_USER_AGENTS.iter().map(|s| s.to_string()).collect(),
+            proxies: Vec::new(),
+            proxy_selection: ProxySelection::default(),
+            proxy_quarantine_secs: 5 * 60,
+            retry: Retry::default(),
+            block_retry: BlockRetry::default(),
+            custom_search: CustomSearchConfig::default(),
+            theme: Theme::default(),
+            keybindings: KeymapConfig::default(),
+            filters: SearchFilters::default(),
+        }
+    }
+}
+
+const DEFAULT_USER_AGENTS: [&str; 3] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_4) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/81.0.4044.138 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 11.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36",
+];
+
+/// [`CacheMode`] of each backend. Scraping is slow and may be blocked, so
+/// it shows expired results while searching again by default; the API
+/// answers quickly and waits for fresh ones.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheModes {
+    pub google: CacheMode,
+    #[serde(rename = "custom-search")]
+    pub custom_search: CacheMode,
+}
+
+impl Default for CacheModes {
+    fn default() -> Self {
+        Self {
+            google: CacheMode::Stale,
+            custom_search: CacheMode::Strict,
+        }
+    }
+}
+
+impl CacheModes {
+    pub fn get(&self, backend: BackendKind) -> CacheMode {
+        match backend {
+            BackendKind::Google => self.google,
+            BackendKind::CustomSearch => self.custom_search,
+        }
+    }
+}
+
+/// Request timeouts in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub search_secs: u64,
+    pub reader_secs: u64,
+    pub crawl_check_secs: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            search_secs: 10,
+            reader_secs: 15,
+            crawl_check_secs: 5,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn search(&self) -> Duration {
+        Duration::from_secs(self.search_secs)
+    }
+
+    pub fn reader(&self) -> Duration {
+        Duration::from_secs(self.reader_secs)
+    }
+
+    pub fn crawl_check(&self) -> Duration {
+        Duration::from_secs(self.crawl_check_secs)
+    }
+}
+
+/// Retries after a search times out or the server fails with a 5xx,
+/// waiting `backoff_secs`, then twice as long each time up to
+/// `max_backoff_secs`, with jitter.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Retry {
+    pub retries: u32,
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff_secs: 1,
+            max_backoff_secs: 8,
+        }
+    }
+}
+
+/// Retries after Google blocks a search, waiting `backoff_secs`, then twice
+/// as long each time up to `max_backoff_secs`, with jitter.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockRetry {
+    pub retries: u32,
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    /// Backend to try once when every attempt was blocked.
+    pub fallback_backend: Option<BackendKind>,
+}
+
+impl Default for BlockRetry {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff_secs: 2,
+            max_backoff_secs: 30,
+            fallback_backend: None,
+        }
+    }
+}
+
+/// Credentials and endpoint of the Custom Search JSON API. Values left out
+/// are read from `GOOGLE_API_KEY`, `GOOGLE_CSE_ID` and `GOOGLE_CSE_BASE_URL`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomSearchConfig {
+    pub api_key: Option<String>,
+    /// Id of the programmable search engine.
+    pub cx: Option<String>,
+    /// The API endpoint, e.g. a local stub server.
+    pub base_url: Option<String>,
+}
+
+/// Colors of the results screen. Accepts names (`lightblue`), indexed
+/// colors (`42`) and hex (`#1e90ff`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(deserialize_with = "color")]
+    pub title: Color,
+    #[serde(deserialize_with = "color")]
+    pub site: Color,
+    #[serde(deserialize_with = "color")]
+    pub url: Color,
+    #[serde(deserialize_with = "color")]
+    pub selection_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub selection_bg: Color,
+    /// Background of query words highlighted in descriptions.
+    #[serde(deserialize_with = "color")]
+    pub highlight: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            title: Color::LightBlue,
+            site: Color::Green,
+            url: Color::Cyan,
+            selection_fg: Color::White,
+            selection_bg: Color::Blue,
+            highlight: Color::Yellow,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = <String as serde::Deserialize>::deserialize(deserializer)?;
+    Color::from_str(&name)
+        .map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", name)))
+}
+
+/// Why the config file couldn't be used.
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    /// Malformed TOML or a value of the wrong type.
+    Parse(PathBuf, String),
+    /// Well-formed values that don't make sense, one message each.
+    Invalid(PathBuf, Vec<String>),
+    /// Command line options that make the config invalid.
+    Overrides(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "Cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Invalid config {}: {}", path.display(), e),
+            ConfigError::Invalid(path, problems) => {
+                write!(f, "Invalid config {}:", path.display())?;
+                write_problems(f, problems)
+            }
+            ConfigError::Overrides(problems) => {
+                write!(f, "Invalid command line options:")?;
+                write_problems(f, problems)
+            }
+        }
+    }
+}
+
+fn write_problems(f: &mut fmt::Formatter, problems: &[String]) -> fmt::Result {
+    for problem in problems {
+        write!(f, "\n  - {}", problem)?;
+    }
+    Ok(())
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// `config.toml` in the platform config dir, e.g.
+    /// `~/.config/terminal_google_search/config.toml` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_DIR).join("config.toml"))
+    }
+
+    /// Reads `path`, or the default path when `None`. A missing default file
+    /// gives the defaults; a missing explicit file is an error.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !explicit && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(ConfigError::Read(path, e)),
+        };
+        let config: Self =
+            toml::from_str(&text).map_err(|e| ConfigError::Parse(path.clone(), e.to_string()))?;
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(path, problems));
+        }
+        Ok(config)
+    }
+
+    /// Everything wrong with the values, empty when the config is usable.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let timeouts = [
+            ("timeouts.search_secs", self.timeouts.search_secs),
+            ("timeouts.reader_secs", self.timeouts.reader_secs),
+            ("timeouts.crawl_check_secs", self.timeouts.crawl_check_secs),
+        ];
+        for (name, secs) in timeouts {
+            if secs == 0 {
+                problems.push(format!("{} must be at least 1", name));
+            }
+        }
+        if self.retry.max_backoff_secs < self.retry.backoff_secs {
+            problems.push("retry.max_backoff_secs must be at least retry.backoff_secs".to_string());
+        }
+        if self.block_retry.max_backoff_secs < self.block_retry.backoff_secs {
+            problems.push(
+                "block_retry.max_backoff_secs must be at least block_retry.backoff_secs"
+                    .to_string(),
+            );
+        }
+        if self.user_agents.iter().all(|ua| ua.trim().is_empty()) {
+            problems.push("user_agents needs at least one user agent".to_string());
+        }
+        for proxy in &self.proxies {
+            if let Err(e) = check_proxy(proxy) {
+                problems.push(format!("proxy '{}': {}", proxy, e));
+            }
+        }
+        if let Some(base_url) = &self.custom_search.base_url {
+            if let Err(e) = check_base_url(base_url) {
+                problems.push(format!("custom_search.base_url '{}': {}", base_url, e));
+            }
+        }
+
+        if let Err(keymap_problems) = Keymap::new(&self.keybindings) {
+            problems.extend(keymap_problems);
+        }
+
+        let region = self.filters.region();
+        let is_country_code = region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic());
+        if !region.is_empty() && !is_country_code {
+            problems.push(format!(
+                "filters.region '{}' should be a two letter country code",
+                region
+            ));
+        }
+        let language = self.filters.language();
+        if !language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            problems.push(format!(
+                "filters.language '{}' should be a language code such as 'en' or 'pt-BR'",
+                language
+            ));
+        }
+        problems
+    }
+
+    /// Directory holding the result cache.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir
+            .clone()
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join(APP_DIR)))
+    }
+
+    /// Directory holding the search history.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir
+            .clone()
+            .or_else(|| dirs::data_local_dir().map(|dir| dir.join(APP_DIR)))
+    }
+}
+
+fn check_proxy(proxy: &str) -> Result<(), String> {
+    let url = Url::parse(proxy).map_err(|e| e.to_string())?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!(
+            "unsupported scheme '{}', use http, https or socks5",
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err("missing host".to_string());
+    }
+    Ok(())
+}
+
+fn check_base_url(base_url: &str) -> Result<(), String> {
+    let url = Url::parse(base_url).map_err(|e| e.to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!(
+            "unsupported scheme '{}', use http or https",
+            url.scheme()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(text: &str) -> Vec<String> {
+        toml::from_str::<Config>(text).unwrap().validate()
+    }
+
+    #[test]
+    fn defaults_and_example_are_valid() {
+        assert!(Config::default().validate().is_empty());
+        let example = include_str!("../config.example.toml");
+        assert!(problems(example).is_empty());
+    }
+
+    #[test]
+    fn reports_zero_timeouts() {
+        assert_eq!(
+            problems("[timeouts]\nsearch_secs = 0\nreader_secs = 0"),
+            [
+                "timeouts.search_secs must be at least 1",
+                "timeouts.reader_secs must be at least 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_bad_proxies() {
+        assert_eq!(
+            problems(r#"proxies = ["ftp://127.0.0.1:21", "not a url", "socks5://127.0.0.1:9050"]"#),
+            [
+                "proxy 'ftp://127.0.0.1:21': unsupported scheme 'ftp', use http, https or socks5",
+                "proxy 'not a url': relative URL without a base",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_bad_custom_search_url() {
+        assert_eq!(
+            problems("[custom_search]\nbase_url = \"ftp://127.0.0.1/v1\""),
+            ["custom_search.base_url 'ftp://127.0.0.1/v1': unsupported scheme 'ftp', use http or https"]
+        );
+        assert!(problems(
+            "[custom_search]\napi_key = \"k\"\ncx = \"c\"\nbase_url = \"http://127.0.0.1:8799\""
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_empty_user_agents() {
+        assert_eq!(
+            problems(r#"user_agents = ["", "  "]"#),
+            ["user_agents needs at least one user agent"]
+        );
+        assert_eq!(
+            problems("user_agents = []"),
+            ["user_agents needs at least one user agent"]
+        );
+    }
+
+    #[test]
+    fn reports_backoffs_and_filters() {
+        assert_eq!(
+            problems(
+                "[retry]\nbackoff_secs = 5\nmax_backoff_secs = 1\n\
+                 [filters]\nregion = \"usa\"\nlanguage = \"en us\""
+            ),
+            [
+                "retry.max_backoff_secs must be at least retry.backoff_secs",
+                "filters.region 'usa' should be a two letter country code",
+                "filters.language 'en us' should be a language code such as 'en' or 'pt-BR'",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = toml::from_str::<Config>("cache_tll_secs = 5").unwrap_err();
+        assert!(err.to_string().contains("unknown field `cache_tll_secs`"));
+        let err = toml::from_str::<Config>("[timeouts]\nsearch = 5").unwrap_err();
+        assert!(err.to_string().contains("unknown field `search`"));
+        let err = toml::from_str::<Config>("[cache_mode]\nbing = \"stale\"").unwrap_err();
+        assert!(err.to_string().contains("unknown field `bing`"));
+    }
+
+    #[test]
+    fn load_reports_every_problem_with_the_path() {
+        let path =
+            std::env::temp_dir().join(format!("cmdgoogle-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "user_agents = []\n[timeouts]\ncrawl_check_secs = 0\n",
+        )
+        .unwrap();
+        let err = Config::load(Some(&path)).unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(&err, ConfigError::Invalid(_, problems) if problems.len() == 2));
+        assert!(message.contains(&path.display().to_string()));
+        assert!(message.contains("timeouts.crawl_check_secs must be at least 1"));
+
+        fs::write(&path, "backend = \"bing\"\n").unwrap();
+        assert!(matches!(
+            Config::load(Some(&path)).unwrap_err(),
+            ConfigError::Parse(..)
+        ));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Config::load(Some(&path)).unwrap_err(),
+            ConfigError::Read(..)
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use super::{USER_AGENT, USER_AGENT_TOKEN};
+use reqwest::{header::CONTENT_TYPE, StatusCode, Url};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Mutex, OnceCell, Semaphore},
+    task::JoinSet,
+};
+
+const MAX_CONCURRENT_CHECKS: usize = 8;
+
+/// Extensions of documents the reader can't display.
+const BINARY_EXTENSIONS: [&str; 30] = [
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "rtf", "csv", "epub", "zip",
+    "gz", "tar", "rar", "7z", "exe", "msi", "dmg", "iso", "apk", "deb", "rpm", "mp3", "mp4", "mkv",
+    "avi", "mov", "webm",
+];
+
+/// Sites that render nothing useful without running JavaScript or logging in.
+const JS_ONLY_DOMAINS: [&str; 14] = [
+    "twitter.com",
+    "x.com",
+    "facebook.com",
+    "instagram.com",
+    "linkedin.com",
+    "tiktok.com",
+    "youtube.com",
+    "pinterest.com",
+    "quora.com",
+    "discord.com",
+    "figma.com",
+    "maps.google.com",
+    "docs.google.com",
+    "drive.google.com",
+];
+
+/// Sites that serve a paywall instead of the article body.
+const PAYWALL_DOMAINS: [&str; 10] = [
+    "nytimes.com",
+    "wsj.com",
+    "ft.com",
+    "bloomberg.com",
+    "economist.com",
+    "washingtonpost.com",
+    "newyorker.com",
+    "theathletic.com",
+    "barrons.com",
+    "thetimes.co.uk",
+];
+
+/// Decides crawlability from the url alone. Returns `false` when the url is
+/// known not to work in the reader, `true` when it still needs a network check.
+pub fn quick_check(url: &str) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = parsed.host_str() else {
+        return false;
+    };
+    if matches_domain(host, &JS_ONLY_DOMAINS) || matches_domain(host, &PAYWALL_DOMAINS) {
+        return false;
+    }
+    let extension = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|last| last.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    !extension.is_some_and(|ext| BINARY_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn matches_domain(host: &str, domains: &[&str]) -> bool {
+    let host = host.trim_start_matches("www.");
+    domains
+        .iter()
+        .any(|d| host == *d || host.ends_with(&format!(".{}", d)))
+}
+
+/// Network checks for every url, run concurrently. Results arrive one at a
+/// time through [`CrawlChecks::try_recv`]; dropping the handle aborts the
+/// checks still running.
+pub struct CrawlChecks {
+    rx: mpsc::UnboundedReceiver<(String, bool)>,
+    pending: HashSet<String>,
+    _tasks: JoinSet<()>,
+}
+
+impl CrawlChecks {
+    /// Each request made with `client` is given `timeout`.
+    pub fn spawn(urls: Vec<String>, client: reqwest::Client, timeout: Duration) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut tasks = JoinSet::new();
+        let robots = Arc::new(RobotsCache::default());
+        let limit = Arc::new(Semaphore::new(MAX_CONCURRENT_CHECKS));
+
+        let pending: HashSet<String> = urls.into_iter().collect();
+        for url in pending.iter().cloned() {
+            let tx = tx.clone();
+            let client = client.clone();
+            let robots = robots.clone();
+            let limit = limit.clone();
+            tasks.spawn(async move {
+                let Ok(_permit) = limit.acquire().await else {
+                    return;
+                };
+                let supported = check_url(&client, timeout, &robots, &url).await;
+                let _ = tx.send((url, supported));
+            });
+        }
+
+        Self {
+            rx,
+            pending,
+            _tasks: tasks,
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<(String, bool)> {
+        let (url, supported) = self.rx.try_recv().ok()?;
+        self.pending.remove(&url);
+        Some((url, supported))
+    }
+
+    pub fn is_pending(&self, url: &str) -> bool {
+        self.pending.contains(url)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+async fn check_url(
+    client: &reqwest::Client,
+    timeout: Duration,
+    robots: &RobotsCache,
+    url: &str,
+) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return false;
+    };
+    if !quick_check(url) {
+        return false;
+    }
+    if !robots.allows(client, timeout, &parsed).await {
+        return false;
+    }
+
+    let resp = match client
+        .head(parsed)
+        .header("User-Agent", USER_AGENT)
+        .timeout(timeout)
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        Err(_) => return false,
+    };
+
+    match resp.status() {
+        // login walls and paywalls
+        StatusCode::UNAUTHORIZED | StatusCode::PAYMENT_REQUIRED | StatusCode::FORBIDDEN => {
+            return false
+        }
+        // some servers don't implement HEAD, the GET may still work
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => return true,
+        status if !status.is_success() => return false,
+        _ => {}
+    }
+
+    if let Some(final_host) = resp.url().host_str() {
+        if matches_domain(final_host, &PAYWALL_DOMAINS) || final_host.starts_with("login.") {
+            return false;
+        }
+    }
+
+    resp.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.contains("html"))
+        .unwrap_or(true)
+}
+
+/// robots.txt files fetched during one batch of checks, one fetch per origin.
+#[derive(Default)]
+struct RobotsCache {
+    origins: Mutex<HashMap<String, Arc<OnceCell<Option<RobotsRules>>>>>,
+}
+
+impl RobotsCache {
+    async fn allows(&self, client: &reqwest::Client, timeout: Duration, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        let cell = self
+            .origins
+            .lock()
+            .await
+            .entry(origin.clone())
+            .or_default()
+            .clone();
+        let rules = cell
+            .get_or_init(|| async {
+                let resp = client
+                    .get(format!("{}/robots.txt", origin))
+                    .header("User-Agent", USER_AGENT)
+                    .timeout(timeout)
+                    .send()
+                    .await
+                    .ok()?;
+                if !resp.status().is_success() {
+                    return None;
+                }
+                let body = resp.text().await.ok()?;
+                Some(RobotsRules::parse(&body, USER_AGENT_TOKEN))
+            })
+            .await;
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        rules.as_ref().is_none_or(|r| r.allows(&path))
+    }
+}
+
+/// The Allow/Disallow rules of the robots.txt group that applies to us.
+#[derive(Debug, Default)]
+struct RobotsRules {
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    /// Picks the group naming `agent`, falling back to the `*` group.
+    fn parse(body: &str, agent: &str) -> Self {
+        let agent = agent.to_ascii_lowercase();
+        let mut specific = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // an empty Disallow means everything is allowed
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (key == "allow", value.to_string());
+                    if group_agents.contains(&agent) {
+                        specific.push(rule.clone());
+                    }
+                    if group_agents.iter().any(|a| a == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            rules: if specific.is_empty() {
+                wildcard
+            } else {
+                specific
+            },
+        }
+    }
+
+    /// The longest matching rule wins, Allow wins ties.
+    fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// robots.txt path matching with `*` wildcards and a `$` end anchor.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
@@ -0,0 +1,9 @@
+pub mod check;
+pub mod reader;
+
+/// User agent sent when fetching result pages for the reader.
+pub const USER_AGENT: &str =
+    "Mozilla/5.0 (compatible; CmdGoogle/0.1; +https://github.com/vTuanpham/CmdGoogle)";
+
+/// Product token matched against robots.txt `User-agent` lines.
+pub const USER_AGENT_TOKEN: &str = "CmdGoogle";
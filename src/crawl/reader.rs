@@ -0,0 +1,289 @@
+use color_eyre::Result;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::time::Duration;
+
+use super::USER_AGENT;
+
+/// Elements that never hold article content.
+const SKIPPED_TAGS: [&str; 14] = [
+    "script", "style", "nav", "header", "footer", "aside", "noscript", "form", "svg", "iframe",
+    "button", "template", "select", "head",
+];
+
+const INLINE_TAGS: [&str; 22] = [
+    "a", "abbr", "b", "bdi", "cite", "code", "data", "em", "i", "kbd", "label", "mark", "q", "s",
+    "samp", "small", "span", "strong", "sub", "sup", "time", "u",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    ListItem {
+        depth: usize,
+        marker: Option<usize>,
+        text: String,
+    },
+    Code(String),
+    Quote(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub text: String,
+    pub href: String,
+}
+
+/// The readable part of a web page.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub url: String,
+    pub title: String,
+    pub blocks: Vec<Block>,
+    pub links: Vec<Link>,
+}
+
+pub async fn fetch_page(client: &reqwest::Client, url: &str, timeout: Duration) -> Result<Page> {
+    let resp = client
+        .get(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "text/html,application/xhtml+xml")
+        .header("Accept-Language", "en-US,en;q=0.9")
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("text/html")
+        .to_string();
+    if !content_type.contains("html") {
+        return Err(color_eyre::eyre::eyre!(
+            "Cannot display '{}' content in the reader",
+            content_type
+        ));
+    }
+
+    let final_url = resp.url().clone();
+    let body = resp.text().await?;
+    Ok(extract_page(&body, &final_url))
+}
+
+pub fn extract_page(html: &str, base_url: &Url) -> Page {
+    let doc = Html::parse_document(html);
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|sel| doc.select(&sel).next())
+        .map(|t| collapse_whitespace(&t.text().collect::<String>()))
+        .unwrap_or_default();
+
+    let root = ["article", "main", "[role=main]", "body"]
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+        .find_map(|sel| doc.select(&sel).next())
+        .unwrap_or_else(|| doc.root_element());
+
+    let mut extractor = Extractor {
+        base_url,
+        blocks: Vec::new(),
+        links: Vec::new(),
+    };
+    extractor.walk_container(root, 0);
+
+    Page {
+        url: base_url.to_string(),
+        title,
+        blocks: extractor.blocks,
+        links: extractor.links,
+    }
+}
+
+struct Extractor<'a> {
+    base_url: &'a Url,
+    blocks: Vec<Block>,
+    links: Vec<Link>,
+}
+
+impl Extractor<'_> {
+    /// Walks a block-level element, turning loose inline content into
+    /// paragraphs and dispatching nested blocks.
+    fn walk_container(&mut self, element: ElementRef, list_depth: usize) {
+        let mut pending = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => pending.push_str(text),
+                Node::Element(el) => {
+                    let Some(child_el) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    let tag = el.name();
+                    if SKIPPED_TAGS.contains(&tag) || is_hidden(&child_el) {
+                        continue;
+                    }
+                    if INLINE_TAGS.contains(&tag) || tag == "br" {
+                        self.collect_inline(child_el, &mut pending);
+                        continue;
+                    }
+                    self.flush_paragraph(&mut pending);
+                    self.walk_block(child_el, tag, list_depth);
+                }
+                _ => {}
+            }
+        }
+        self.flush_paragraph(&mut pending);
+    }
+
+    fn walk_block(&mut self, element: ElementRef, tag: &str, list_depth: usize) {
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = tag[1..].parse().unwrap_or(1);
+                let text = self.inline_text(element);
+                if !text.is_empty() {
+                    self.blocks.push(Block::Heading(level, text));
+                }
+            }
+            "p" => {
+                let text = self.inline_text(element);
+                if !text.is_empty() {
+                    self.blocks.push(Block::Paragraph(text));
+                }
+            }
+            "pre" => {
+                let code = element.text().collect::<String>();
+                let code = code.trim_matches('\n').to_string();
+                if !code.trim().is_empty() {
+                    self.blocks.push(Block::Code(code));
+                }
+            }
+            "blockquote" => {
+                let text = self.inline_text(element);
+                if !text.is_empty() {
+                    self.blocks.push(Block::Quote(text));
+                }
+            }
+            "ul" | "ol" => self.walk_list(element, tag == "ol", list_depth),
+            "img" | "picture" | "video" | "audio" | "canvas" | "hr" => {}
+            _ => self.walk_container(element, list_depth),
+        }
+    }
+
+    fn walk_list(&mut self, list: ElementRef, ordered: bool, depth: usize) {
+        let mut number = 0;
+        for item in list.children().filter_map(ElementRef::wrap) {
+            if item.value().name() != "li" {
+                continue;
+            }
+            number += 1;
+            let mut text = String::new();
+            let mut nested = Vec::new();
+            for child in item.children() {
+                match child.value() {
+                    Node::Text(t) => text.push_str(t),
+                    Node::Element(el) => {
+                        let Some(child_el) = ElementRef::wrap(child) else {
+                            continue;
+                        };
+                        match el.name() {
+                            "ul" | "ol" => nested.push(child_el),
+                            name if SKIPPED_TAGS.contains(&name) => {}
+                            _ => {
+                                self.collect_inline(child_el, &mut text);
+                                text.push(' ');
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let text = collapse_whitespace(&text);
+            if !text.is_empty() {
+                self.blocks.push(Block::ListItem {
+                    depth,
+                    marker: ordered.then_some(number),
+                    text,
+                });
+            }
+            for nested_list in nested {
+                let nested_ordered = nested_list.value().name() == "ol";
+                self.walk_list(nested_list, nested_ordered, depth + 1);
+            }
+        }
+    }
+
+    fn inline_text(&mut self, element: ElementRef) -> String {
+        let mut out = String::new();
+        self.collect_inline(element, &mut out);
+        collapse_whitespace(&out)
+    }
+
+    fn collect_inline(&mut self, element: ElementRef, out: &mut String) {
+        match element.value().name() {
+            "br" => {
+                out.push(' ');
+                return;
+            }
+            "a" => {
+                let text = collapse_whitespace(&element.text().collect::<String>());
+                out.push_str(&text);
+                if let Some(href) = element.value().attr("href").and_then(|h| self.resolve(h)) {
+                    self.links.push(Link {
+                        text: text.clone(),
+                        href,
+                    });
+                    out.push_str(&format!(" [{}]", self.links.len()));
+                }
+                return;
+            }
+            "code" => {
+                out.push('`');
+                out.push_str(&element.text().collect::<String>());
+                out.push('`');
+                return;
+            }
+            _ => {}
+        }
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(text),
+                Node::Element(el) if !SKIPPED_TAGS.contains(&el.name()) => {
+                    if let Some(child_el) = ElementRef::wrap(child) {
+                        self.collect_inline(child_el, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn flush_paragraph(&mut self, pending: &mut String) {
+        let text = collapse_whitespace(pending);
+        if !text.is_empty() {
+            self.blocks.push(Block::Paragraph(text));
+        }
+        pending.clear();
+    }
+
+    fn resolve(&self, href: &str) -> Option<String> {
+        if href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        self.base_url.join(href).ok().map(|u| u.to_string())
+    }
+}
+
+fn is_hidden(element: &ElementRef) -> bool {
+    let el = element.value();
+    el.attr("hidden").is_some()
+        || el.attr("aria-hidden") == Some("true")
+        || el
+            .attr("style")
+            .is_some_and(|s| s.replace(' ', "").contains("display:none"))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,138 @@
+use crate::search::{QueryResult, SerpFeatures};
+use bincode::Options;
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+
+/// The last single-file layout, a map from cache key to these.
+#[derive(Debug, Deserialize)]
+pub struct CachedResult {
+    pub results: Vec<QueryResult>,
+    pub features: SerpFeatures,
+    pub timestamp: u64,
+}
+
+/// `QueryResult` as cached before vertical results added source, date,
+/// thumbnail and duration.
+#[derive(Debug, Deserialize)]
+struct QueryResultV1 {
+    url: String,
+    title: String,
+    display_url: String,
+    site_name: String,
+    description: String,
+    url_supported_flag: bool,
+}
+
+impl From<QueryResultV1> for QueryResult {
+    fn from(v1: QueryResultV1) -> Self {
+        Self {
+            url: v1.url,
+            title: v1.title,
+            display_url: v1.display_url,
+            site_name: v1.site_name,
+            description: v1.description,
+            url_supported_flag: v1.url_supported_flag,
+            ..Default::default()
+        }
+    }
+}
+
+/// `CachedResult` as written before answer boxes were cached.
+#[derive(Debug, Deserialize)]
+struct CachedResultV1 {
+    results: Vec<QueryResultV1>,
+    timestamp: u64,
+}
+
+impl From<CachedResultV1> for CachedResult {
+    fn from(v1: CachedResultV1) -> Self {
+        Self {
+            results: v1.results.into_iter().map(Into::into).collect(),
+            features: SerpFeatures::default(),
+            timestamp: v1.timestamp,
+        }
+    }
+}
+
+/// `CachedResult` as written before vertical results had their own fields.
+#[derive(Debug, Deserialize)]
+struct CachedResultV2 {
+    results: Vec<QueryResultV1>,
+    features: SerpFeatures,
+    timestamp: u64,
+}
+
+impl From<CachedResultV2> for CachedResult {
+    fn from(v2: CachedResultV2) -> Self {
+        Self {
+            results: v2.results.into_iter().map(Into::into).collect(),
+            features: v2.features,
+            timestamp: v2.timestamp,
+        }
+    }
+}
+
+/// `QueryResult` as cached before titles and site names were captured.
+#[derive(Debug, Deserialize)]
+struct LegacyQueryResult {
+    url: String,
+    description: String,
+    url_supported_flag: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegacyCachedResult {
+    results: Vec<LegacyQueryResult>,
+    timestamp: u64,
+}
+
+impl From<LegacyCachedResult> for CachedResult {
+    fn from(legacy: LegacyCachedResult) -> Self {
+        let results = legacy
+            .results
+            .into_iter()
+            .map(|r| {
+                QueryResult {
+                    url: r.url,
+                    title: String::new(),
+                    display_url: String::new(),
+                    site_name: String::new(),
+                    description: r.description,
+                    url_supported_flag: r.url_supported_flag,
+                    ..Default::default()
+                }
+                .with_fallbacks()
+            })
+            .collect();
+        Self {
+            results,
+            features: SerpFeatures::default(),
+            timestamp: legacy.timestamp,
+        }
+    }
+}
+
+/// Reads `search_cache.bin`, the single-file cache used before the record
+/// store, trying its layouts from newest to oldest. Trailing bytes are
+/// rejected so one layout can't be mistaken for another.
+pub fn decode(bytes: &[u8]) -> Option<HashMap<String, CachedResult>> {
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes();
+    if let Ok(data) = options.deserialize(bytes) {
+        return Some(data);
+    }
+    if let Ok(v2) = options.deserialize::<HashMap<String, CachedResultV2>>(bytes) {
+        return Some(v2.into_iter().map(|(q, c)| (q, c.into())).collect());
+    }
+    if let Ok(v1) = options.deserialize::<HashMap<String, CachedResultV1>>(bytes) {
+        return Some(v1.into_iter().map(|(q, c)| (q, c.into())).collect());
+    }
+    let legacy: HashMap<String, LegacyCachedResult> = options.deserialize(bytes).ok()?;
+    Some(
+        legacy
+            .into_iter()
+            .map(|(query, cached)| (query, cached.into()))
+            .collect(),
+    )
+}
@@ -0,0 +1,45 @@
+use super::store::FORMAT_VERSION;
+use super::CachedPage;
+use crate::search::{QueryResult, SerpFeatures};
+use serde_derive::Deserialize;
+
+/// `CachedPage` up to format 2, before the search was kept with it.
+#[derive(Debug, Deserialize)]
+struct CachedPageV2 {
+    results: Vec<QueryResult>,
+    features: SerpFeatures,
+}
+
+/// Brings a payload written in format `version` up to the current format,
+/// one version at a time, or returns `None` if it can't be.
+///
+/// A change to what is cached (`CachedPage` and the search types in it)
+/// bumps [`FORMAT_VERSION`] and adds a step here that decodes the previous
+/// layout, kept as structs of its own like those in `legacy`, and encodes
+/// it as the new one.
+pub fn upgrade(version: u32, mut payload: Vec<u8>) -> Option<Vec<u8>> {
+    for from in version..FORMAT_VERSION {
+        payload = match from {
+            // only the file header was added
+            1 => payload,
+            // the search is unknown, the cache tells it from the key where
+            // it can
+            2 => {
+                let v2: CachedPageV2 = bincode::deserialize(&payload).ok()?;
+                bincode::serialize(&CachedPage {
+                    results: v2.results,
+                    features: v2.features,
+                    search: None,
+                })
+                .ok()?
+            }
+            _ => return None,
+        };
+    }
+    Some(payload)
+}
+
+/// Reads a payload written in format `version`.
+pub fn decode(version: u32, payload: Vec<u8>) -> Option<CachedPage> {
+    bincode::deserialize(&upgrade(version, payload)?).ok()
+}
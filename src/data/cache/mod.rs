@@ -0,0 +1,474 @@
+mod legacy;
+mod migrate;
+mod store;
+
+use super::persist::back_up_corrupt;
+use crate::config::Config;
+use crate::search::{
+    BackendKind, QueryArgs, QueryResult, SearchFilters, SearchPage, SerpFeatures, Vertical,
+    CACHE_KEY_SEPARATOR,
+};
+use crate::utils::{format_age, format_size};
+use color_eyre::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use store::{FormatError, Store};
+
+pub use store::{Stop, Verification, FORMAT_VERSION};
+
+const STORE_FILE: &str = "search_cache.dat";
+/// The single-file cache used before the record store.
+const LEGACY_FILE: &str = "search_cache.bin";
+
+/// What is stored for one cache key.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPage {
+    results: Vec<QueryResult>,
+    features: SerpFeatures,
+    /// `None` for pages cached before it was kept.
+    search: Option<CachedSearch>,
+}
+
+/// The search a page came from, to run it again.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedSearch {
+    query: String,
+    page: usize,
+    num: usize,
+    filters: SearchFilters,
+    vertical: Vertical,
+}
+
+impl From<&QueryArgs> for CachedSearch {
+    fn from(args: &QueryArgs) -> Self {
+        Self {
+            query: args.query.clone(),
+            page: args.page,
+            num: args.num,
+            filters: args.filters.clone(),
+            vertical: args.vertical,
+        }
+    }
+}
+
+impl From<CachedSearch> for QueryArgs {
+    fn from(search: CachedSearch) -> Self {
+        Self {
+            query: search.query,
+            page: search.page,
+            num: search.num,
+            filters: search.filters,
+            vertical: search.vertical,
+            ..Default::default()
+        }
+    }
+}
+
+/// What a search does with a page past the cache lifetime.
+#[derive(PartialEq, Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheMode {
+    /// Search again and wait for the results.
+    Strict,
+    /// Show the old page at once and search again in the background.
+    Stale,
+}
+
+/// A page found in the cache.
+pub struct CacheHit {
+    pub page: SearchPage,
+    /// How long ago it was stored, when that's past the cache lifetime.
+    pub stale_age: Option<u64>,
+}
+
+/// A cached page as listed by `cache list` and the cache panel.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub key: String,
+    /// The query and, when it isn't the first unfiltered page of web
+    /// results, the rest of the key.
+    pub query: String,
+    pub params: Option<String>,
+    /// When it was stored, in seconds since the epoch.
+    pub stored: u64,
+    /// Size of the stored page.
+    pub size: u64,
+    /// `None` when the page can't be read.
+    pub results: Option<usize>,
+    /// Past the cache lifetime, so it's searched again when next used.
+    pub expired: bool,
+    /// The search that refreshes it, when known, and the backend it goes
+    /// to.
+    pub search: Option<QueryArgs>,
+    pub backend: BackendKind,
+}
+
+impl CacheEntry {
+    /// One line with its age, result count, size and key.
+    pub fn describe(&self, now: u64) -> String {
+        let results = match self.results {
+            Some(1) => "1 result ".to_string(),
+            Some(count) => format!("{} results", count),
+            None => "? results".to_string(),
+        };
+        let mut line = format!(
+            "{:<4} {:>11} {:>9}  {}",
+            format_age(now.saturating_sub(self.stored)),
+            results,
+            format_size(self.size),
+            self.query
+        );
+        if let Some(params) = &self.params {
+            line.push_str(&format!(" [{}]", params));
+        }
+        if self.expired {
+            line.push_str(" (expired)");
+        }
+        line
+    }
+}
+
+/// What `cache stats` shows.
+pub struct CacheStats {
+    pub path: PathBuf,
+    pub entries: usize,
+    pub expired: usize,
+    /// Size of the file and of the pages in it.
+    pub file_len: u64,
+    pub payload_len: u64,
+    /// Cap on `payload_len`, 0 for none.
+    pub max_bytes: u64,
+    /// When the oldest and newest pages were stored.
+    pub oldest: Option<u64>,
+    pub newest: Option<u64>,
+}
+
+/// Search results by cache key, kept in a record store so only the pages
+/// asked for are read and each change is a single append.
+pub struct Cache {
+    path: PathBuf,
+    /// `None` when the cache file can't be used, which `warnings` explains;
+    /// nothing is cached then.
+    store: Option<Store>,
+    ttl: Duration,
+    pub cache_hit: bool,
+    pub enable_cache_hit_notification: bool,
+    /// Problems met while loading, for the UI to show; see
+    /// [`Cache::take_warnings`].
+    warnings: Vec<String>,
+    /// What loading did to bring older caches up to date.
+    pub upgrades: Vec<String>,
+}
+
+/// What `cache verify` found.
+pub struct CacheStatus {
+    pub path: PathBuf,
+    /// `None` when there is no cache file.
+    pub store: Option<Verification>,
+    /// An old single-file cache waiting to be imported, with its number of
+    /// entries or `None` when it's unreadable.
+    pub legacy: Option<(PathBuf, Option<usize>)>,
+}
+
+impl Cache {
+    pub async fn load(config: &Config) -> Result<Self> {
+        let dir = Self::get_cache_dir(config).await?;
+        let mut warnings = Vec::new();
+        let mut upgrades = Vec::new();
+        let path = dir.join(STORE_FILE);
+        let mut store = match Store::open(
+            &path,
+            config.cache_max_mb.saturating_mul(1024 * 1024),
+            &migrate::upgrade,
+            &mut warnings,
+        ) {
+            Ok(store) => Some(store),
+            Err(e) => match e.downcast::<FormatError>() {
+                Ok(e) => {
+                    warnings.push(e.to_string());
+                    None
+                }
+                Err(e) => return Err(e),
+            },
+        };
+        if let Some(version) = store.as_ref().and_then(|store| store.upgraded_from) {
+            upgrades.push(format!(
+                "Upgraded the search cache from format {} to {}",
+                version, FORMAT_VERSION
+            ));
+        }
+        let legacy = dir.join(LEGACY_FILE);
+        if let (Some(store), true) = (&mut store, legacy.exists()) {
+            let imported = Self::import_legacy(store, &legacy, &mut warnings)?;
+            if imported > 0 {
+                upgrades.push(format!(
+                    "Imported {} cached pages from {}",
+                    imported,
+                    legacy.display()
+                ));
+            }
+        }
+        Ok(Self {
+            path,
+            store,
+            ttl: Duration::from_secs(config.cache_ttl_secs),
+            cache_hit: false,
+            enable_cache_hit_notification: config.cache_hit_notification,
+            warnings,
+            upgrades,
+        })
+    }
+
+    /// The problems met since the last call, loading included.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        if let Some(store) = &mut self.store {
+            warnings.append(&mut store.warnings);
+        }
+        warnings
+    }
+
+    /// Whether results are being cached.
+    pub fn is_enabled(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// Checks the cache files without changing them.
+    pub async fn verify(config: &Config) -> Result<CacheStatus> {
+        let dir = Self::get_cache_dir(config).await?;
+        let path = dir.join(STORE_FILE);
+        let store = Store::verify(&path, &|version, payload| {
+            migrate::decode(version, payload).is_some()
+        })?;
+        let legacy = dir.join(LEGACY_FILE);
+        let legacy = if legacy.exists() {
+            let entries = legacy::decode(&fs::read(&legacy)?).map(|entries| entries.len());
+            Some((legacy, entries))
+        } else {
+            None
+        };
+        Ok(CacheStatus {
+            path,
+            store,
+            legacy,
+        })
+    }
+
+    async fn get_cache_dir(config: &Config) -> Result<PathBuf> {
+        let app_cache_dir = config
+            .cache_dir()
+            .ok_or(color_eyre::eyre::eyre!("No cache directory found"))?;
+        fs::create_dir_all(&app_cache_dir)?;
+        Ok(app_cache_dir)
+    }
+
+    /// Moves the entries of the old single-file cache into the store, oldest
+    /// first so eviction keeps the newest, deletes the file and returns how
+    /// many entries it had. An unreadable file is backed up instead, with a
+    /// warning.
+    fn import_legacy(store: &mut Store, path: &Path, warnings: &mut Vec<String>) -> Result<usize> {
+        let Some(entries) = legacy::decode(&fs::read(path)?) else {
+            warnings.push(back_up_corrupt(path, "old search cache"));
+            return Ok(0);
+        };
+        let count = entries.len();
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.timestamp);
+        for (key, entry) in entries {
+            let page = CachedPage {
+                results: entry.results,
+                features: entry.features,
+                search: None,
+            };
+            store.put(&key, &bincode::serialize(&page)?, entry.timestamp)?;
+        }
+        fs::remove_file(path)?;
+        Ok(count)
+    }
+
+    /// The page cached under `query`. One past the cache lifetime is
+    /// returned with its age in [`CacheMode::Stale`], and dropped in
+    /// [`CacheMode::Strict`].
+    pub async fn get(&mut self, query: &str, mode: CacheMode) -> Result<Option<CacheHit>> {
+        let Some(store) = &mut self.store else {
+            return Ok(None);
+        };
+        store.refresh()?;
+        let Some(slot) = store.slot(query) else {
+            return Ok(None);
+        };
+        let now = now_secs()?;
+        let age = now.saturating_sub(slot.stored);
+        let expired = age >= self.ttl.as_secs();
+        if expired && mode == CacheMode::Strict {
+            store.remove(query)?;
+            return Ok(None);
+        }
+        let Some(bytes) = store.get(query, now)? else {
+            return Ok(None);
+        };
+        match bincode::deserialize::<CachedPage>(&bytes) {
+            Ok(cached) => Ok(Some(CacheHit {
+                page: SearchPage {
+                    results: cached.results,
+                    strategy: None,
+                    features: cached.features,
+                },
+                stale_age: expired.then_some(age),
+            })),
+            Err(_) => {
+                store.remove(query)?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn insert(
+        &mut self,
+        args: &QueryArgs,
+        backend: BackendKind,
+        page: &SearchPage,
+    ) -> Result<()> {
+        let Some(store) = &mut self.store else {
+            return Ok(());
+        };
+        let cached = CachedPage {
+            results: page.results.clone(),
+            features: page.features.clone(),
+            search: Some(args.into()),
+        };
+        store.put(
+            &args.cache_key(backend),
+            &bincode::serialize(&cached)?,
+            now_secs()?,
+        )
+    }
+
+    /// Every cached page, newest first, without counting them as used.
+    pub fn entries(&mut self) -> Result<Vec<CacheEntry>> {
+        let Some(store) = &mut self.store else {
+            return Ok(Vec::new());
+        };
+        store.refresh()?;
+        let now = now_secs()?;
+        let mut entries = Vec::new();
+        for (key, slot) in store.slots() {
+            let cached = store
+                .peek(key)?
+                .and_then(|bytes| bincode::deserialize::<CachedPage>(&bytes).ok());
+            let (query, params) = match key.split_once(CACHE_KEY_SEPARATOR) {
+                Some((query, params)) => (query.to_string(), Some(params.to_string())),
+                None => (key.clone(), None),
+            };
+            let results = cached.as_ref().map(|cached| cached.results.len());
+            let search = match cached.and_then(|cached| cached.search) {
+                Some(search) => Some(search.into()),
+                // a bare key is the first unfiltered page of web results
+                None if params.is_none() => Some(QueryArgs {
+                    query: key.clone(),
+                    ..Default::default()
+                }),
+                None => None,
+            };
+            entries.push(CacheEntry {
+                key: key.clone(),
+                query,
+                params,
+                stored: slot.stored,
+                size: slot.size(),
+                results,
+                expired: now.saturating_sub(slot.stored) >= self.ttl.as_secs(),
+                search,
+                backend: BackendKind::of_cache_key(key),
+            });
+        }
+        entries.sort_by(|a, b| b.stored.cmp(&a.stored).then_with(|| a.key.cmp(&b.key)));
+        Ok(entries)
+    }
+
+    /// The page stored under `key`, expired or not, without counting it as
+    /// used.
+    pub fn peek(&mut self, key: &str) -> Result<Option<SearchPage>> {
+        let Some(store) = &mut self.store else {
+            return Ok(None);
+        };
+        store.refresh()?;
+        let Some(bytes) = store.peek(key)? else {
+            return Ok(None);
+        };
+        Ok(bincode::deserialize::<CachedPage>(&bytes)
+            .ok()
+            .map(|cached| SearchPage {
+                results: cached.results,
+                strategy: None,
+                features: cached.features,
+            }))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<()> {
+        match &mut self.store {
+            Some(store) => store.remove(key),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes every page, or those stored longer ago than `older_than`,
+    /// and returns how many.
+    pub fn purge(&mut self, older_than: Option<Duration>) -> Result<usize> {
+        let Some(store) = &mut self.store else {
+            return Ok(0);
+        };
+        store.refresh()?;
+        let now = now_secs()?;
+        let keys: Vec<String> = store
+            .slots()
+            .filter(|(_, slot)| match older_than {
+                Some(age) => now.saturating_sub(slot.stored) > age.as_secs(),
+                None => true,
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            store.remove(key)?;
+        }
+        Ok(keys.len())
+    }
+
+    pub fn stats(&mut self) -> Result<CacheStats> {
+        let mut stats = CacheStats {
+            path: self.path.clone(),
+            entries: 0,
+            expired: 0,
+            file_len: 0,
+            payload_len: 0,
+            max_bytes: 0,
+            oldest: None,
+            newest: None,
+        };
+        let Some(store) = &mut self.store else {
+            return Ok(stats);
+        };
+        store.refresh()?;
+        let now = now_secs()?;
+        for (_, slot) in store.slots() {
+            stats.entries += 1;
+            if now.saturating_sub(slot.stored) >= self.ttl.as_secs() {
+                stats.expired += 1;
+            }
+            stats.oldest = Some(stats.oldest.map_or(slot.stored, |t| t.min(slot.stored)));
+            stats.newest = Some(stats.newest.map_or(slot.stored, |t| t.max(slot.stored)));
+        }
+        stats.file_len = store.file_len();
+        stats.payload_len = store.payload_len();
+        stats.max_bytes = store.max_bytes();
+        Ok(stats)
+    }
+}
+
+pub fn now_secs() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
@@ -0,0 +1,1134 @@
+use crate::data::persist;
+use color_eyre::Result;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Starts every cache file, followed by the format version.
+const MAGIC: [u8; 8] = *b"CGCACHE\0";
+const HEADER_LEN: u64 = 12;
+
+/// The format this version writes. It goes up whenever the records or the
+/// payloads change, and older files are upgraded when opened; see
+/// `migrate` for what each version changed.
+pub const FORMAT_VERSION: u32 = 3;
+
+const PUT: u8 = 0;
+const TOUCH: u8 = 1;
+const REMOVE: u8 = 2;
+
+/// Files smaller than this are never compacted.
+const MIN_COMPACT_BYTES: u64 = 1024 * 1024;
+
+/// Reads of an entry are only written to the file this long after the last
+/// one that was, in seconds. Eviction goes by hours and days, and a touch
+/// on every read would fill the file with records.
+const TOUCH_INTERVAL: u64 = 60 * 60;
+
+/// Where an entry's payload sits in the file, and when it was stored and
+/// last read, in seconds since the epoch.
+#[derive(Debug, Clone, Copy)]
+pub struct Slot {
+    offset: u64,
+    len: u32,
+    /// Size of the put record, payload included.
+    record_len: u64,
+    pub stored: u64,
+    pub used: u64,
+    /// The last read time written to the file.
+    recorded: u64,
+}
+
+impl Slot {
+    /// Size of the payload.
+    pub fn size(&self) -> u64 {
+        self.len as u64
+    }
+}
+
+/// An append-only file of cache records with an index of where every live
+/// entry's payload is.
+///
+/// Opening reads only keys and times; payloads are read when asked for.
+/// Every change is one record appended at the end: a put with the payload,
+/// a touch when an entry is read (at most every [`TOUCH_INTERVAL`], more
+/// recent reads only count in memory until the next compaction) or a remove.
+/// Once most of the file is
+/// superseded records it is rewritten with the live entries only.
+///
+/// Several instances can share the file. Each holds a shared lock on
+/// `<path>.lock` while it's open, appends under an exclusive lock on the
+/// file itself after catching up with what the others appended, and only
+/// compacts when it can lock `<path>.lock` exclusively, i.e. when no other
+/// instance has the file open.
+///
+/// The file starts with [`MAGIC`] and the format version as a `u32`.
+/// Records are a kind byte and a length-prefixed key, then for a put the
+/// stored and used times and the length-prefixed payload, for a touch the
+/// used time, and nothing more for a remove. Integers are little-endian.
+pub struct Store {
+    path: PathBuf,
+    file: File,
+    /// Shared lock held while the store is open.
+    presence: File,
+    index: HashMap<String, Slot>,
+    /// Length of the file.
+    len: u64,
+    /// Bytes of the put records still in the index.
+    live: u64,
+    /// Payload bytes of the entries in the index.
+    payload: u64,
+    max_bytes: u64,
+    /// The format the file was in when it had to be upgraded on opening.
+    pub upgraded_from: Option<u32>,
+    /// Unreadable records found and backed up by later writes, for the
+    /// caller to show like the warnings of [`Store::open`].
+    pub warnings: Vec<String>,
+}
+
+impl Store {
+    /// Opens or creates the store at `path`. `max_bytes` caps the total size
+    /// of the payloads; 0 leaves it unbounded.
+    ///
+    /// When no other instance has it open, a record cut short by a crash is
+    /// dropped, and unreadable records are backed up with a warning pushed
+    /// to `warnings` before being dropped along with anything after them.
+    /// A file in an older format is then rewritten in the current one, its
+    /// payloads passed through `upgrade` with the format they were written
+    /// in; those it returns `None` for are dropped. Opening fails with a
+    /// [`FormatError`] for a file this version can't use.
+    pub fn open(
+        path: &Path,
+        max_bytes: u64,
+        upgrade: &dyn Fn(u32, Vec<u8>) -> Option<Vec<u8>>,
+        warnings: &mut Vec<String>,
+    ) -> Result<Self> {
+        let presence = persist::open_lock_file(path)?;
+        let alone = try_lock(&presence)?;
+        if !alone {
+            presence.lock_shared()?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let version = start_file(&file)?;
+        if version > FORMAT_VERSION {
+            return Err(FormatError::Newer(version).into());
+        }
+        if version < FORMAT_VERSION && !alone {
+            return Err(FormatError::InUse(version).into());
+        }
+        let mut store = Self {
+            path: path.to_path_buf(),
+            file,
+            presence,
+            index: HashMap::new(),
+            len: records_start(version),
+            live: 0,
+            payload: 0,
+            max_bytes,
+            upgraded_from: None,
+            warnings: Vec::new(),
+        };
+        let stop = store.catch_up()?;
+        if alone {
+            warnings.extend(store.recover(stop)?);
+            if version < FORMAT_VERSION {
+                let dropped = store.rewrite(&|payload| upgrade(version, payload))?;
+                if dropped > 0 {
+                    warnings.push(format!(
+                        "{} cached {} couldn't be upgraded to the new cache format and {} dropped",
+                        dropped,
+                        if dropped == 1 { "page" } else { "pages" },
+                        if dropped == 1 { "was" } else { "were" },
+                    ));
+                }
+                store.upgraded_from = Some(version);
+            }
+            store.presence.unlock()?;
+            store.presence.lock_shared()?;
+        }
+        Ok(store)
+    }
+
+    /// Reads the store at `path` without changing it, passing the payload
+    /// of every entry to `check`, or returns `None` if there's no file.
+    pub fn verify(
+        path: &Path,
+        check: &dyn Fn(u32, Vec<u8>) -> bool,
+    ) -> Result<Option<Verification>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // writers hold an exclusive lock while appending
+        file.lock_shared()?;
+        let len = file.metadata()?.len();
+        let mut verification = Verification {
+            version: FORMAT_VERSION,
+            len,
+            entries: 0,
+            superseded: 0,
+            unreadable: Vec::new(),
+            stop: Stop::End,
+            stopped_at: len,
+        };
+        let version = match read_header(&file)? {
+            Header::Empty => return Ok(Some(verification)),
+            Header::Torn => {
+                verification.stop = Stop::Torn;
+                verification.stopped_at = 0;
+                return Ok(Some(verification));
+            }
+            Header::Version(version) => version,
+        };
+        verification.version = version;
+        if version > FORMAT_VERSION {
+            return Ok(Some(verification));
+        }
+        let start = records_start(version);
+        let mut records = Vec::new();
+        let (end, stop) = read_records(&file, start, len, &mut records)?;
+        let mut index = HashMap::new();
+        for (key, record) in records {
+            match record {
+                Record::Put(slot) => {
+                    index.insert(key, slot);
+                }
+                Record::Touch { .. } => {}
+                Record::Remove => {
+                    index.remove(&key);
+                }
+            }
+        }
+        let live: u64 = index.values().map(|slot| slot.record_len).sum();
+        for (key, slot) in &index {
+            if !check(version, read_payload(&file, slot)?) {
+                verification.unreadable.push(key.clone());
+            }
+        }
+        verification.unreadable.sort();
+        verification.entries = index.len();
+        verification.superseded = end - start - live;
+        verification.stop = stop;
+        verification.stopped_at = end;
+        Ok(Some(verification))
+    }
+
+    /// Replays the records other instances appended since the last call.
+    /// Returns why reading stopped, leaving `len` at the first record that
+    /// couldn't be read.
+    fn catch_up(&mut self) -> Result<Stop> {
+        let file_len = self.file.metadata()?.len();
+        if file_len == self.len {
+            return Ok(Stop::End);
+        }
+        let mut records = Vec::new();
+        let (pos, stop) = read_records(&self.file, self.len, file_len, &mut records)?;
+        for (key, record) in records {
+            self.apply(key, record);
+        }
+        self.len = pos;
+        Ok(stop)
+    }
+
+    /// Cuts off whatever follows the last readable record. Only safe when
+    /// no one else is appending.
+    fn recover(&mut self, stop: Stop) -> Result<Option<String>> {
+        let warning = match stop {
+            Stop::End => return Ok(None),
+            Stop::Torn => None,
+            Stop::Corrupt => {
+                let backup = persist::backup_path(&self.path);
+                fs::copy(&self.path, &backup)?;
+                Some(format!(
+                    "Part of the search cache was unreadable and has been dropped; \
+                     the file was backed up to {}",
+                    backup.display()
+                ))
+            }
+        };
+        self.file.set_len(self.len)?;
+        Ok(warning)
+    }
+
+    /// Picks up what other instances stored since the last change.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.catch_up()?;
+        Ok(())
+    }
+
+    fn apply(&mut self, key: String, record: Record) {
+        match record {
+            Record::Put(slot) => {
+                self.live += slot.record_len;
+                self.payload += slot.len as u64;
+                if let Some(old) = self.index.insert(key, slot) {
+                    self.forget(&old);
+                }
+            }
+            Record::Touch { used } => {
+                if let Some(slot) = self.index.get_mut(&key) {
+                    slot.used = slot.used.max(used);
+                    slot.recorded = used;
+                }
+            }
+            Record::Remove => {
+                if let Some(old) = self.index.remove(&key) {
+                    self.forget(&old);
+                }
+            }
+        }
+    }
+
+    fn forget(&mut self, slot: &Slot) {
+        self.live -= slot.record_len;
+        self.payload -= slot.len as u64;
+    }
+
+    pub fn slot(&self, key: &str) -> Option<Slot> {
+        self.index.get(key).copied()
+    }
+
+    /// Every entry's key and slot, in no particular order.
+    pub fn slots(&self) -> impl Iterator<Item = (&String, &Slot)> {
+        self.index.iter()
+    }
+
+    /// Size of the file.
+    pub fn file_len(&self) -> u64 {
+        self.len
+    }
+
+    /// Total size of the payloads.
+    pub fn payload_len(&self) -> u64 {
+        self.payload
+    }
+
+    /// The cap on [`Store::payload_len`], 0 for none.
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Reads the payload of `key` without counting it as used.
+    pub fn peek(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.index.get(key) {
+            Some(slot) => Ok(Some(read_payload(&self.file, slot)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the payload of `key` and records the read for eviction. A
+    /// touch that can't be written is pushed to `warnings` rather than
+    /// failing the read.
+    pub fn get(&mut self, key: &str, now: u64) -> Result<Option<Vec<u8>>> {
+        let Some(slot) = self.index.get_mut(key) else {
+            return Ok(None);
+        };
+        slot.used = slot.used.max(now);
+        let slot = *slot;
+        let payload = read_payload(&self.file, &slot)?;
+        if now >= slot.recorded.saturating_add(TOUCH_INTERVAL) {
+            match self.append(key, &Record::Touch { used: now }) {
+                Ok(()) => {
+                    if let Some(slot) = self.index.get_mut(key) {
+                        slot.recorded = now;
+                    }
+                }
+                Err(e) => self
+                    .warnings
+                    .push(format!("Couldn't record a read in the search cache: {}", e)),
+            }
+        }
+        Ok(Some(payload))
+    }
+
+    /// Stores `payload` under `key`, then evicts the least recently used
+    /// entries while the cap is exceeded.
+    pub fn put(&mut self, key: &str, payload: &[u8], now: u64) -> Result<()> {
+        let slot = Slot {
+            offset: 0,
+            len: payload.len().try_into()?,
+            record_len: 0,
+            stored: now,
+            used: now,
+            recorded: now,
+        };
+        let (offset, record_len) = self.append_put(key, &slot, payload)?;
+        self.apply(
+            key.to_string(),
+            Record::Put(Slot {
+                offset: offset + record_len - payload.len() as u64,
+                record_len,
+                ..slot
+            }),
+        );
+        self.evict()?;
+        self.compact_if_sparse()
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<()> {
+        if !self.index.contains_key(key) {
+            return Ok(());
+        }
+        self.append(key, &Record::Remove)?;
+        self.apply(key.to_string(), Record::Remove);
+        self.compact_if_sparse()
+    }
+
+    fn evict(&mut self) -> Result<()> {
+        while self.max_bytes > 0 && self.payload > self.max_bytes && self.index.len() > 1 {
+            let Some(oldest) = self
+                .index
+                .iter()
+                .min_by_key(|(_, slot)| slot.used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.append(&oldest, &Record::Remove)?;
+            self.apply(oldest, Record::Remove);
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, key: &str, record: &Record) -> Result<()> {
+        let mut buf = Vec::new();
+        write_header(&mut buf, record.kind(), key)?;
+        if let Record::Touch { used } = record {
+            buf.extend_from_slice(&used.to_le_bytes());
+        }
+        self.write(&buf)?;
+        Ok(())
+    }
+
+    /// Appends a put record and returns its length.
+    fn append_put(&mut self, key: &str, slot: &Slot, payload: &[u8]) -> Result<(u64, u64)> {
+        let mut buf = Vec::with_capacity(payload.len() + key.len() + 32);
+        write_put(&mut buf, key, slot, payload)?;
+        let offset = self.write(&buf)?;
+        Ok((offset, buf.len() as u64))
+    }
+
+    /// Appends `buf` holding the file's lock, after the records other
+    /// instances added, and returns where it was written.
+    fn write(&mut self, buf: &[u8]) -> Result<u64> {
+        self.file.lock()?;
+        let result = self.write_locked(buf);
+        self.file.unlock()?;
+        result
+    }
+
+    fn write_locked(&mut self, buf: &[u8]) -> Result<u64> {
+        let stop = self.catch_up()?;
+        // nobody else is writing, so an unreadable tail isn't a record in
+        // progress
+        if let Some(warning) = self.recover(stop)? {
+            self.warnings.push(warning);
+        }
+        let offset = self.len;
+        self.file.write_all(buf)?;
+        self.len += buf.len() as u64;
+        Ok(offset)
+    }
+
+    /// Rewrites the file with only the live entries once superseded records
+    /// take up more than half of it, unless another instance has it open.
+    fn compact_if_sparse(&mut self) -> Result<()> {
+        if self.len < MIN_COMPACT_BYTES || self.len < self.live * 2 {
+            return Ok(());
+        }
+        self.presence.unlock()?;
+        let result = match try_lock(&self.presence) {
+            Ok(true) => {
+                let result = self.compact();
+                self.presence.unlock()?;
+                result
+            }
+            Ok(false) => Ok(()),
+            Err(e) => Err(e),
+        };
+        self.presence.lock_shared()?;
+        result
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        self.rewrite(&Some)?;
+        Ok(())
+    }
+
+    /// Writes the live entries in the current format to a new file, their
+    /// payloads passed through `convert`, and renames it over the old.
+    /// Returns how many entries were dropped because `convert` couldn't
+    /// read them. The caller must be the only instance with the file open.
+    fn rewrite(&mut self, convert: &dyn Fn(Vec<u8>) -> Option<Vec<u8>>) -> Result<usize> {
+        let tmp = persist::temp_path(&self.path);
+        let mut index = HashMap::with_capacity(self.index.len());
+        let mut len = HEADER_LEN;
+        let mut payload_bytes = 0;
+        let mut dropped = 0;
+        {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            write_file_header(&mut out)?;
+            let mut entries: Vec<_> = self.index.iter().collect();
+            entries.sort_by_key(|(_, slot)| slot.offset);
+            for (key, slot) in entries {
+                let Some(payload) = convert(read_payload(&self.file, slot)?) else {
+                    dropped += 1;
+                    continue;
+                };
+                let mut buf = Vec::with_capacity(payload.len() + key.len() + 32);
+                write_put(&mut buf, key, slot, &payload)?;
+                out.write_all(&buf)?;
+                let record_len = buf.len() as u64;
+                index.insert(
+                    key.clone(),
+                    Slot {
+                        offset: len + record_len - payload.len() as u64,
+                        len: payload.len().try_into()?,
+                        record_len,
+                        recorded: slot.used,
+                        ..*slot
+                    },
+                );
+                len += record_len;
+                payload_bytes += payload.len() as u64;
+            }
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        self.index = index;
+        self.len = len;
+        self.live = len - HEADER_LEN;
+        self.payload = payload_bytes;
+        Ok(dropped)
+    }
+}
+
+/// What [`Store::verify`] found.
+#[derive(Debug)]
+pub struct Verification {
+    pub version: u32,
+    /// Size of the file.
+    pub len: u64,
+    pub entries: usize,
+    /// Bytes of records that a later one replaced or removed.
+    pub superseded: u64,
+    /// Keys of the entries whose payload couldn't be read.
+    pub unreadable: Vec<String>,
+    /// Why reading stopped, and where.
+    pub stop: Stop,
+    pub stopped_at: u64,
+}
+
+/// Why a cache file can't be opened by this version.
+#[derive(Debug)]
+pub enum FormatError {
+    /// Written by a newer version, in the given format.
+    Newer(u32),
+    /// In an older format, which can't be upgraded while another instance
+    /// has the file open.
+    InUse(u32),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Newer(version) => write!(
+                f,
+                "The search cache was written by a newer version (format {}, this one reads up to {}); \
+                 results won't be cached until it's updated",
+                version, FORMAT_VERSION
+            ),
+            FormatError::InUse(version) => write!(
+                f,
+                "The search cache is in an older format ({}) and another instance has it open; \
+                 results won't be cached until it's closed",
+                version
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Why reading records stopped.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Stop {
+    End,
+    /// The last record runs past the end of the file, which is what a
+    /// crash while appending leaves.
+    Torn,
+    /// A record is malformed.
+    Corrupt,
+}
+
+/// Takes an exclusive lock on `file` if nobody holds any lock on it.
+fn try_lock(file: &File) -> Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+enum Header {
+    Empty,
+    /// The file was cut short while the header was written.
+    Torn,
+    Version(u32),
+}
+
+/// Reads the format version from the start of `file`. A file that doesn't
+/// start with [`MAGIC`] is a headerless version 1 file.
+fn read_header(mut file: &File) -> io::Result<Header> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN as usize);
+    file.seek(SeekFrom::Start(0))?;
+    file.take(HEADER_LEN).read_to_end(&mut bytes)?;
+    let magic_len = bytes.len().min(MAGIC.len());
+    Ok(if bytes.is_empty() {
+        Header::Empty
+    } else if bytes[..magic_len] != MAGIC[..magic_len] {
+        Header::Version(1)
+    } else if bytes.len() < HEADER_LEN as usize {
+        Header::Torn
+    } else {
+        Header::Version(u32::from_le_bytes(bytes[8..12].try_into().unwrap()))
+    })
+}
+
+/// Returns the format of `file`, writing the header first if it's new or
+/// was cut short while being created.
+fn start_file(file: &File) -> Result<u32> {
+    file.lock()?;
+    let result = (|| {
+        Ok(match read_header(file)? {
+            Header::Version(version) => version,
+            Header::Empty | Header::Torn => {
+                file.set_len(0)?;
+                let mut file = file;
+                write_file_header(&mut file)?;
+                FORMAT_VERSION
+            }
+        })
+    })();
+    file.unlock()?;
+    result
+}
+
+fn write_file_header(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(&MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())
+}
+
+/// Where the records of a file in `version` start.
+fn records_start(version: u32) -> u64 {
+    if version == 1 {
+        0
+    } else {
+        HEADER_LEN
+    }
+}
+
+enum Record {
+    Put(Slot),
+    Touch { used: u64 },
+    Remove,
+}
+
+impl Record {
+    fn kind(&self) -> u8 {
+        match self {
+            Record::Put(_) => PUT,
+            Record::Touch { .. } => TOUCH,
+            Record::Remove => REMOVE,
+        }
+    }
+}
+
+fn write_header(buf: &mut Vec<u8>, kind: u8, key: &str) -> Result<()> {
+    let key_len: u32 = key.len().try_into()?;
+    buf.push(kind);
+    buf.extend_from_slice(&key_len.to_le_bytes());
+    buf.extend_from_slice(key.as_bytes());
+    Ok(())
+}
+
+fn write_put(buf: &mut Vec<u8>, key: &str, slot: &Slot, payload: &[u8]) -> Result<()> {
+    write_header(buf, PUT, key)?;
+    buf.extend_from_slice(&slot.stored.to_le_bytes());
+    buf.extend_from_slice(&slot.used.to_le_bytes());
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(payload);
+    Ok(())
+}
+
+/// Reads the records from `pos` on into `records`, returning where reading
+/// stopped and why. A record running past the end of the file only counts
+/// as torn when nothing readable follows it; with whole records after it,
+/// its length is damaged and it's corrupt.
+fn read_records(
+    mut file: &File,
+    mut pos: u64,
+    file_len: u64,
+    records: &mut Vec<(String, Record)>,
+) -> io::Result<(u64, Stop)> {
+    file.seek(SeekFrom::Start(pos))?;
+    let mut reader = BufReader::new(file);
+    let stop = loop {
+        match read_record(&mut reader, pos, file_len) {
+            Ok(Some((key, record, len))) => {
+                pos += len;
+                records.push((key, record));
+            }
+            Ok(None) => break Stop::End,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                let mut tail = Vec::with_capacity((file_len - pos) as usize);
+                file.seek(SeekFrom::Start(pos))?;
+                file.take(file_len - pos).read_to_end(&mut tail)?;
+                break if holds_records(&tail) {
+                    Stop::Corrupt
+                } else {
+                    Stop::Torn
+                };
+            }
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => break Stop::Corrupt,
+            Err(e) => return Err(e),
+        }
+    };
+    Ok((pos, stop))
+}
+
+/// Whether whole records end `tail` from anywhere after its first byte.
+/// Keys are never empty, which keeps the odd run of zeros in a torn
+/// payload from passing for records.
+fn holds_records(tail: &[u8]) -> bool {
+    (1..tail.len()).any(|start| {
+        let rest = &tail[start..];
+        let mut reader = io::Cursor::new(rest);
+        let mut pos = 0;
+        loop {
+            match read_record(&mut reader, pos, rest.len() as u64) {
+                Ok(Some((key, _, len))) if !key.is_empty() => pos += len,
+                Ok(None) => return pos > 0,
+                _ => return false,
+            }
+        }
+    })
+}
+
+/// Moves a reader past bytes without reading them.
+trait Skip {
+    fn skip(&mut self, n: u64) -> io::Result<()>;
+}
+
+impl Skip for BufReader<&File> {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        self.seek_relative(n as i64)
+    }
+}
+
+impl Skip for io::Cursor<&[u8]> {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        self.set_position(self.position() + n);
+        Ok(())
+    }
+}
+
+fn read_payload(mut file: &File, slot: &Slot) -> io::Result<Vec<u8>> {
+    let mut payload = vec![0; slot.len as usize];
+    file.seek(SeekFrom::Start(slot.offset))?;
+    file.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Reads the record starting at `pos`, returning its key, contents and
+/// length, or `None` at the end of the file. A record running past
+/// `file_len` is `UnexpectedEof`, an unknown kind or a key that isn't UTF-8
+/// `InvalidData`.
+fn read_record(
+    reader: &mut (impl Read + Skip),
+    pos: u64,
+    file_len: u64,
+) -> io::Result<Option<(String, Record, u64)>> {
+    let mut kind = [0; 1];
+    if reader.read(&mut kind)? == 0 {
+        return Ok(None);
+    }
+    let key_len = read_u32(reader)? as u64;
+    let mut len = 1 + 4 + key_len;
+    if pos + len > file_len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut key = vec![0; key_len as usize];
+    reader.read_exact(&mut key)?;
+    let key = String::from_utf8(key).map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+    let record = match kind[0] {
+        PUT => {
+            let stored = read_u64(reader)?;
+            let used = read_u64(reader)?;
+            let payload_len = read_u32(reader)?;
+            let offset = pos + len + 20;
+            len += 20 + payload_len as u64;
+            if pos + len > file_len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            reader.skip(payload_len as u64)?;
+            Record::Put(Slot {
+                offset,
+                len: payload_len,
+                record_len: len,
+                stored,
+                used,
+                recorded: used,
+            })
+        }
+        TOUCH => {
+            len += 8;
+            Record::Touch {
+                used: read_u64(reader)?,
+            }
+        }
+        REMOVE => Record::Remove,
+        _ => return Err(io::ErrorKind::InvalidData.into()),
+    };
+    Ok(Some((key, record, len)))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::cache::{migrate, CachedPage};
+    use crate::search::{QueryResult, SerpFeatures};
+    use serde_derive::Serialize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A directory of its own under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "cmdgoogle-store-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self) -> PathBuf {
+            self.0.join("cache.dat")
+        }
+
+        fn backups(&self) -> Vec<PathBuf> {
+            fs::read_dir(&self.0)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.to_string_lossy().contains(".corrupt-"))
+                .collect()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn open(path: &Path, max_bytes: u64) -> (Store, Vec<String>) {
+        let mut warnings = Vec::new();
+        let store =
+            Store::open(path, max_bytes, &|_, payload| Some(payload), &mut warnings).unwrap();
+        (store, warnings)
+    }
+
+    fn keys(store: &Store) -> Vec<&str> {
+        let mut keys: Vec<&str> = store.slots().map(|(key, _)| key.as_str()).collect();
+        keys.sort();
+        keys
+    }
+
+    /// Length of the put record of `key` holding `payload_len` bytes.
+    fn put_len(key: &str, payload_len: usize) -> u64 {
+        (1 + 4 + key.len() + 20 + payload_len) as u64
+    }
+
+    #[test]
+    fn round_trip_and_reopen() {
+        let dir = TempDir::new();
+        let (mut store, warnings) = open(&dir.file(), 0);
+        assert!(warnings.is_empty());
+        store.put("a", b"first", 10).unwrap();
+        store.put("b", b"second", 11).unwrap();
+        store.put("a", b"replaced", 12).unwrap();
+        store.put("c", b"third", 13).unwrap();
+        store.remove("c").unwrap();
+        let read = 12 + TOUCH_INTERVAL;
+        assert_eq!(store.get("a", read).unwrap().unwrap(), b"replaced");
+        assert_eq!(store.get("c", read).unwrap(), None);
+        assert_eq!(store.payload_len(), 14);
+        drop(store);
+
+        let (store, warnings) = open(&dir.file(), 0);
+        assert!(warnings.is_empty());
+        assert_eq!(keys(&store), ["a", "b"]);
+        let a = store.slot("a").unwrap();
+        assert_eq!((a.stored, a.used), (12, read));
+        assert_eq!(store.peek("b").unwrap().unwrap(), b"second");
+        assert_eq!(store.payload_len(), 14);
+    }
+
+    #[test]
+    fn drops_a_truncated_last_record() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"kept", 1).unwrap();
+        store.put("b", b"cut short", 2).unwrap();
+        let len = store.file_len();
+        drop(store);
+        let file = OpenOptions::new().write(true).open(dir.file()).unwrap();
+        file.set_len(len - 3).unwrap();
+        drop(file);
+
+        let (mut store, warnings) = open(&dir.file(), 0);
+        assert!(warnings.is_empty());
+        assert!(dir.backups().is_empty());
+        assert_eq!(keys(&store), ["a"]);
+        assert_eq!(store.file_len(), HEADER_LEN + put_len("a", 4));
+        store.put("c", b"after", 3).unwrap();
+        drop(store);
+        let (store, _) = open(&dir.file(), 0);
+        assert_eq!(keys(&store), ["a", "c"]);
+    }
+
+    #[test]
+    fn backs_up_a_corrupt_record() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"kept", 1).unwrap();
+        store.put("b", b"damaged", 2).unwrap();
+        store.put("c", b"after it", 3).unwrap();
+        drop(store);
+        let mut bytes = fs::read(dir.file()).unwrap();
+        bytes[(HEADER_LEN + put_len("a", 4)) as usize] = 9;
+        fs::write(dir.file(), &bytes).unwrap();
+
+        let (store, warnings) = open(&dir.file(), 0);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(".corrupt-"));
+        assert_eq!(dir.backups().len(), 1);
+        assert_eq!(fs::read(&dir.backups()[0]).unwrap(), bytes);
+        assert_eq!(keys(&store), ["a"]);
+    }
+
+    #[test]
+    fn backs_up_records_after_a_damaged_length() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"kept", 1).unwrap();
+        store.put("b", b"damaged", 2).unwrap();
+        store.put("c", b"after it", 3).unwrap();
+        drop(store);
+        let mut bytes = fs::read(dir.file()).unwrap();
+        // the payload length of "b" now runs past the end of the file
+        let at = (HEADER_LEN + put_len("a", 4)) as usize + 1 + 4 + 1 + 16;
+        bytes[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(dir.file(), &bytes).unwrap();
+
+        let (store, warnings) = open(&dir.file(), 0);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(dir.backups().len(), 1);
+        assert_eq!(keys(&store), ["a"]);
+    }
+
+    #[test]
+    fn reports_corruption_found_while_writing() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"kept", 1).unwrap();
+        // another writer left a malformed record behind
+        let mut file = OpenOptions::new().append(true).open(dir.file()).unwrap();
+        file.write_all(&[9, 1, 0, 0, 0, b'x']).unwrap();
+        drop(file);
+
+        assert!(store.warnings.is_empty());
+        store.put("b", b"next", 2).unwrap();
+        assert_eq!(store.warnings.len(), 1);
+        assert_eq!(dir.backups().len(), 1);
+        drop(store);
+        let (store, warnings) = open(&dir.file(), 0);
+        assert!(warnings.is_empty());
+        assert_eq!(keys(&store), ["a", "b"]);
+    }
+
+    #[test]
+    fn writes_reads_once_per_interval() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"page", 100).unwrap();
+        let len = store.file_len();
+        for now in [101, 200, 99 + TOUCH_INTERVAL] {
+            assert_eq!(store.get("a", now).unwrap().unwrap(), b"page");
+            assert_eq!(store.slot("a").unwrap().used, now);
+        }
+        assert_eq!(store.file_len(), len);
+
+        let touch_len = (1 + 4 + 1 + 8) as u64;
+        store.get("a", 100 + TOUCH_INTERVAL).unwrap();
+        assert_eq!(store.file_len(), len + touch_len);
+        store.get("a", 101 + TOUCH_INTERVAL).unwrap();
+        assert_eq!(store.file_len(), len + touch_len);
+        drop(store);
+
+        let (store, _) = open(&dir.file(), 0);
+        assert_eq!(store.slot("a").unwrap().used, 100 + TOUCH_INTERVAL);
+    }
+
+    #[test]
+    fn keeps_unwritten_reads_through_compaction() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"page", 1).unwrap();
+        store.get("a", 2).unwrap();
+        store.compact().unwrap();
+        store.get("a", 1 + TOUCH_INTERVAL).unwrap();
+        assert_eq!(store.file_len(), HEADER_LEN + put_len("a", 4));
+        drop(store);
+
+        let (store, _) = open(&dir.file(), 0);
+        assert_eq!(store.slot("a").unwrap().used, 2);
+    }
+
+    #[test]
+    fn returns_the_page_when_a_read_cant_be_written() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        store.put("a", b"page", 1).unwrap();
+        store.file = File::open(dir.file()).unwrap();
+
+        let read = 1 + TOUCH_INTERVAL;
+        assert_eq!(store.get("a", read).unwrap().unwrap(), b"page");
+        assert_eq!(store.slot("a").unwrap().used, read);
+        assert_eq!(store.warnings.len(), 1);
+        assert!(store.warnings[0].starts_with("Couldn't record a read"));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 250);
+        store.put("a", &[0; 100], 1).unwrap();
+        store.put("b", &[0; 100], 2).unwrap();
+        // stored first, but read last
+        store.get("a", 3).unwrap();
+        store.put("c", &[0; 100], 4).unwrap();
+        assert_eq!(keys(&store), ["a", "c"]);
+        store.put("d", &[0; 100], 5).unwrap();
+        assert_eq!(keys(&store), ["c", "d"]);
+        assert_eq!(store.payload_len(), 200);
+        drop(store);
+        let (store, _) = open(&dir.file(), 250);
+        assert_eq!(keys(&store), ["c", "d"]);
+    }
+
+    #[test]
+    fn compacts_keeping_live_entries() {
+        let dir = TempDir::new();
+        let (mut store, _) = open(&dir.file(), 0);
+        let big = vec![7; 300 * 1024];
+        store.put("kept", b"small", 1).unwrap();
+        for now in 2..10 {
+            store.put("big", &big, now).unwrap();
+        }
+        store.put("gone", b"removed", 10).unwrap();
+        store.remove("gone").unwrap();
+        // eight copies of "big" would be over the threshold
+        assert!(store.file_len() < MIN_COMPACT_BYTES);
+        assert_eq!(keys(&store), ["big", "kept"]);
+        assert_eq!(store.peek("big").unwrap().unwrap(), big);
+        assert_eq!(store.peek("kept").unwrap().unwrap(), b"small");
+        drop(store);
+
+        let (store, _) = open(&dir.file(), 0);
+        assert_eq!(keys(&store), ["big", "kept"]);
+        assert_eq!(store.slot("big").unwrap().stored, 9);
+        assert_eq!(store.peek("kept").unwrap().unwrap(), b"small");
+    }
+
+    /// `CachedPage` as format 2 wrote it.
+    #[derive(Serialize)]
+    struct PageV2 {
+        results: Vec<QueryResult>,
+        features: SerpFeatures,
+    }
+
+    #[test]
+    fn upgrades_a_version_2_file() {
+        let dir = TempDir::new();
+        let result = QueryResult {
+            url: "https://docs.rs".to_string(),
+            title: "Docs".to_string(),
+            ..Default::default()
+        };
+        let v2 = bincode::serialize(&PageV2 {
+            results: vec![result.clone()],
+            features: SerpFeatures::default(),
+        })
+        .unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        let slot = Slot {
+            offset: 0,
+            len: 0,
+            record_len: 0,
+            stored: 5,
+            used: 6,
+            recorded: 6,
+        };
+        write_put(&mut bytes, "rust", &slot, &v2).unwrap();
+        write_put(&mut bytes, "broken", &slot, b"not a page").unwrap();
+        fs::write(dir.file(), &bytes).unwrap();
+
+        let mut warnings = Vec::new();
+        let store = Store::open(&dir.file(), 0, &migrate::upgrade, &mut warnings).unwrap();
+        assert_eq!(store.upgraded_from, Some(2));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(keys(&store), ["rust"]);
+        let page: CachedPage = bincode::deserialize(&store.peek("rust").unwrap().unwrap()).unwrap();
+        assert_eq!(page.results, [result]);
+        assert!(page.search.is_none());
+        assert_eq!(store.slot("rust").unwrap().stored, 5);
+        drop(store);
+
+        let (store, warnings) = open(&dir.file(), 0);
+        assert!(warnings.is_empty());
+        assert_eq!(store.upgraded_from, None);
+        assert_eq!(keys(&store), ["rust"]);
+        assert!(migrate::upgrade(2, b"not a page".to_vec()).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use super::persist::{self, back_up_corrupt, write_atomic};
+use crate::config::Config;
+use color_eyre::{eyre::eyre, Result};
+use reqwest::Url;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use std::{
+    convert::Infallible,
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Answer to Google's cookie consent page that declines everything optional.
+/// It is what the "Reject all" button sets.
+const CONSENT_COOKIE: &str = "SOCS=CAI";
+
+/// How long the consent answer is kept, as Google itself does.
+const CONSENT_MAX_AGE: Duration = Duration::from_secs(13 * 30 * 24 * 60 * 60);
+
+/// Cookies shared by every HTTP client, kept next to the search cache so
+/// Google sees the same browser from one run to the next.
+pub struct CookieJar {
+    store: Arc<CookieStoreMutex>,
+    path: PathBuf,
+    /// Problems met while loading or saving, for the UI to show.
+    warnings: Mutex<Vec<String>>,
+}
+
+impl CookieJar {
+    /// A missing file gives an empty jar; an unreadable one is backed up
+    /// with a warning first.
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = Self::get_cookie_file_path(config)?;
+        let mut warnings = Vec::new();
+        let store = Self::read(&path, &mut warnings)?;
+        Ok(Self {
+            store: Arc::new(CookieStoreMutex::new(store)),
+            path,
+            warnings: Mutex::new(warnings),
+        })
+    }
+
+    /// Reads the cookies in `path`, none if it doesn't exist. A file that
+    /// can't be read as cookies is backed up with a warning.
+    fn read(path: &Path, warnings: &mut Vec<String>) -> Result<CookieStore> {
+        match fs::File::open(path) {
+            Ok(file) => Ok(
+                cookie_store::serde::json::load(BufReader::new(file)).unwrap_or_else(|_| {
+                    warnings.push(back_up_corrupt(path, "cookie"));
+                    CookieStore::default()
+                }),
+            ),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(CookieStore::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_cookie_file_path(config: &Config) -> Result<PathBuf> {
+        let app_cache_dir = config
+            .cache_dir()
+            .ok_or(eyre!("No cache directory found"))?;
+        fs::create_dir_all(&app_cache_dir)?;
+        Ok(app_cache_dir.join("cookies.json"))
+    }
+
+    /// The store to hand to `reqwest::ClientBuilder::cookie_provider`.
+    pub fn provider(&self) -> Arc<CookieStoreMutex> {
+        self.store.clone()
+    }
+
+    /// Takes the problems met since the last call, for the UI to show.
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut self.warnings.lock().unwrap())
+    }
+
+    /// Writes the persistent cookies, leaving out session and expired ones.
+    /// Holding the file's lock, the cookies other sessions saved meanwhile
+    /// are merged in first, ours winning where both have one.
+    pub async fn save(&self) -> Result<()> {
+        let _lock = persist::lock_exclusive(&self.path).await?;
+        let on_disk = Self::read(&self.path, &mut self.warnings.lock().unwrap())?;
+        let mut store = self.store.lock().unwrap();
+        let theirs = on_disk.iter_any().filter(|cookie| {
+            !store.contains_any(&String::from(&cookie.domain), &cookie.path, cookie.name())
+        });
+        let merged = theirs
+            .chain(store.iter_any())
+            .cloned()
+            .map(Ok::<_, Infallible>);
+        *store = CookieStore::from_cookies(merged, false).unwrap_or_else(|e| match e {});
+        let mut json = Vec::new();
+        cookie_store::serde::json::save(&store, &mut json).map_err(|e| eyre!(e))?;
+        drop(store);
+        write_atomic(&self.path, &json)?;
+        Ok(())
+    }
+
+    /// Records an answer to the consent page so Google serves results
+    /// instead of redirecting to `consent.google.com`.
+    pub fn accept_google_consent(&self) -> Result<()> {
+        let url = Url::parse("https://www.google.com/")?;
+        let cookie = format!(
+            "{}; Domain=.google.com; Path=/; Max-Age={}; Secure",
+            CONSENT_COOKIE,
+            CONSENT_MAX_AGE.as_secs()
+        );
+        let mut store = self.store.lock().unwrap();
+        store.parse(&cookie, &url).map_err(|e| eyre!(e))?;
+        Ok(())
+    }
+}
@@ -1,55 +1,97 @@
+use super::persist::{self, back_up_corrupt, write_atomic};
+use crate::config::Config;
 use color_eyre::Result;
-use std::{fs, path::PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 pub struct History {
+    path: PathBuf,
     pub search_history: Vec<String>,
+    /// Queries run since the last save, newest first. They go ahead of the
+    /// file's contents on save so concurrent sessions don't drop each
+    /// other's queries.
+    added: Vec<String>,
     pub index: usize,
     pub show_history_popup: bool,
+    /// Problems met while loading or saving, for the UI to show.
+    pub warnings: Vec<String>,
 }
 
 impl History {
-    pub async fn load() -> Result<Self> {
-        let history_path = Self::get_history_file_path().await?;
-        let search_history = if history_path.exists() {
-            let contents = fs::read_to_string(history_path)?;
-            contents.lines().map(|s| s.trim_end().to_string()).collect()
-        } else {
-            Vec::new()
-        };
+    pub async fn load(config: &Config) -> Result<Self> {
+        let path = Self::get_history_file_path(config).await?;
+        let mut warnings = Vec::new();
+        let search_history = Self::read(&path, &mut warnings)?;
 
         Ok(Self {
+            path,
             search_history,
+            added: Vec::new(),
             index: 0,
             show_history_popup: false,
+            warnings,
         })
     }
 
-    async fn get_history_file_path() -> Result<PathBuf> {
-        let data_dir = dirs::data_local_dir()
+    async fn get_history_file_path(config: &Config) -> Result<PathBuf> {
+        let app_dir = config
+            .data_dir()
             .ok_or(color_eyre::eyre::eyre!("No local data directory found"))?;
-        let app_dir = data_dir.join("terminal_google_search");
         fs::create_dir_all(&app_dir)?;
         Ok(app_dir.join("search_history.txt"))
     }
 
-    pub async fn save(&self) -> Result<()> {
-        let history_path = Self::get_history_file_path().await?;
-        let mut file = fs::File::create(history_path)?;
-        for query in &self.search_history {
-            use std::io::Write;
-            writeln!(file, "{}", query)?;
+    /// Reads the queries in `path`, none if it doesn't exist. A file that
+    /// isn't valid text is backed up with a warning.
+    fn read(path: &Path, warnings: &mut Vec<String>) -> Result<Vec<String>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(contents.lines().map(|s| s.trim_end().to_string()).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                warnings.push(back_up_corrupt(path, "search history"));
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e.into()),
         }
+    }
+
+    /// Merges this session's queries into the file as it is now, holding
+    /// its lock, and picks up the queries other sessions saved meanwhile.
+    pub async fn save(&mut self) -> Result<()> {
+        let _lock = persist::lock_exclusive(&self.path).await?;
+        let on_disk = Self::read(&self.path, &mut self.warnings)?;
+        let mut merged = self.added.clone();
+        merged.extend(on_disk.into_iter().filter(|q| !self.added.contains(q)));
+        let mut contents = String::new();
+        for query in &merged {
+            contents.push_str(query);
+            contents.push('\n');
+        }
+        write_atomic(&self.path, contents.as_bytes())?;
+        self.search_history = merged;
+        self.added.clear();
         Ok(())
     }
 
-    pub async fn add_query(&mut self, query: String) {
+    /// Puts `query` first and saves the history. The query stays in memory,
+    /// to be saved with the next one, if saving fails.
+    pub async fn add_query(&mut self, query: String) -> Result<()> {
         if let Some(pos) = self.search_history.iter().position(|x| x == &query) {
             self.search_history.remove(pos);
         }
         self.search_history.insert(0, query.clone());
+        self.added.retain(|q| q != &query);
+        self.added.insert(0, query);
         self.index = 0;
         self.show_history_popup = false;
-        let _ = self.save().await;
+        self.save().await
+    }
+
+    /// Takes the problems met since the last call, for the UI to show.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
     }
 
     pub fn next(&mut self) {
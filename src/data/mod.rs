@@ -1,2 +1,4 @@
 pub mod cache;
+pub mod cookies;
 pub mod history;
+pub mod persist;
@@ -0,0 +1,105 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long [`lock_exclusive`] waits for another process to let go of a
+/// lock, and how often it checks.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_POLL: Duration = Duration::from_millis(20);
+
+/// Replaces `path` with `contents` so that readers, and the file after a
+/// crash, see either the old or the new contents in full. The data goes to
+/// a temporary file next to it, is synced, then renamed over it.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = temp_path(path);
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// A temporary file next to `path`, unique to this process.
+pub fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, &format!(".{}.tmp", std::process::id()))
+}
+
+/// The file whose advisory lock guards `path`. The lock lives on a file of
+/// its own as `path` itself is replaced by renames.
+pub fn lock_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".lock")
+}
+
+/// Opens the lock file of `path` and waits for an exclusive lock on it,
+/// released when the returned file is dropped. It polls rather than blocking
+/// the runtime's thread, and gives up after [`LOCK_TIMEOUT`].
+pub async fn lock_exclusive(path: &Path) -> io::Result<File> {
+    let file = open_lock_file(path)?;
+    let start = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => {
+                tokio::time::sleep(LOCK_POLL).await
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    format!("{} is locked by another process", path.display()),
+                ))
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+}
+
+pub fn open_lock_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(path))
+}
+
+/// Moves an unreadable file out of the way so it isn't overwritten, and
+/// returns the warning to show. `what` names the file for the user.
+pub fn back_up_corrupt(path: &Path, what: &str) -> String {
+    let backup = backup_path(path);
+    match fs::rename(path, &backup) {
+        Ok(()) => format!(
+            "The {} file was unreadable and has been moved to {}",
+            what,
+            backup.display()
+        ),
+        Err(e) => format!(
+            "The {} file {} is unreadable and couldn't be moved aside: {}",
+            what,
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// Where an unreadable `path` is kept, stamped with the time.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    with_suffix(path, &format!(".corrupt-{}", secs))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
@@ -1,72 +1,136 @@
 use crate::app::App;
+use crate::keymap::{Action, Mode};
 use crate::ui::{DisplayMode, InputMode};
 use color_eyre::Result;
-use crossterm::event::{self, Event as CEvent, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event as CEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::time::Duration;
 
 pub async fn handle_events(app: &mut App) -> Result<()> {
     if event::poll(Duration::from_millis(100))? {
         if let CEvent::Key(key) = event::read()? {
-            match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('e') => {
-                        app.display_mode = DisplayMode::Home;
-                        app.input_mode = InputMode::Editing;
-                    }
-                    KeyCode::Char('q') => app.should_quit = true,
-                    KeyCode::Char('d') => app.toggle_debug_mode(),
-                    KeyCode::Char('n') => app.toggle_cache_notification(),
-                    KeyCode::Up | KeyCode::Char('k') => app.previous_result(),
-                    KeyCode::Down | KeyCode::Char('j') => app.next_result(),
-                    KeyCode::Char('c') => app.clear_input(),
-                    KeyCode::Char('o') => app.open_url(),
-                    _ => {}
-                },
-                InputMode::Editing if key.kind == KeyEventKind::Press => {
-                    app.messages.clear();
-                    app.error_message = None;
-                    app.has_entered = false;
-                    match key.code {
-                        KeyCode::Enter => {
-                            app.submit().await?;
-                            app.input_mode = InputMode::Normal;
-                            app.has_entered = true;
-                            app.history.index = 0;
-                            app.history.show_history_popup = false;
-                        }
-                        KeyCode::Char(c) => {
-                            app.insert_char(c);
-                            app.history.index = 0;
-                            app.history.show_history_popup = false;
-                        }
-                        KeyCode::Backspace => {
-                            app.delete_char();
-                            app.history.index = 0;
-                            app.history.show_history_popup = false;
-                        }
-                        KeyCode::Up => {
-                            if !app.history.search_history.is_empty() {
-                                app.previous_history();
-                                app.set_input_to_history();
-                            }
-                        }
-                        KeyCode::Down => {
-                            if app.history.show_history_popup
-                                && !app.history.search_history.is_empty()
-                            {
-                                app.next_history();
-                                app.set_input_to_history();
-                            }
-                        }
-                        KeyCode::Left => app.move_cursor_left(),
-                        KeyCode::Right => app.move_cursor_right(),
-                        KeyCode::Esc => app.exit_input_mode(),
-                        _ => {}
+            if key.kind == KeyEventKind::Press {
+                handle_key(app, key).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_key(app: &mut App, key: KeyEvent) -> Result<()> {
+    let mode = app.key_mode();
+    if mode == Mode::Editing {
+        app.messages.clear();
+        app.error_message = None;
+        app.search_error = None;
+        app.has_entered = false;
+    }
+    match app.keymap.action(mode, &key) {
+        Some(action) => perform(app, mode, action).await?,
+        None if mode.takes_text() => {
+            let chorded = key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if let (KeyCode::Char(c), false) = (key.code, chorded) {
+                match mode {
+                    Mode::Filters => app.filter_insert_char(c),
+                    _ => {
+                        app.insert_char(c);
+                        app.history.index = 0;
+                        app.history.show_history_popup = false;
                     }
                 }
-                _ => {}
             }
         }
+        None => {}
+    }
+    Ok(())
+}
+
+async fn perform(app: &mut App, mode: Mode, action: Action) -> Result<()> {
+    match (mode, action) {
+        (_, Action::Quit) => app.should_quit = true,
+
+        (Mode::Searching, Action::Cancel) => app.cancel_search(),
+
+        (Mode::Reader, Action::Close) => app.close_reader(),
+        (Mode::Reader, Action::Up) => app.scroll_reader(-1),
+        (Mode::Reader, Action::Down) => app.scroll_reader(1),
+        (Mode::Reader, Action::PageUp) => app.scroll_reader(-20),
+        (Mode::Reader, Action::PageDown) => app.scroll_reader(20),
+        (Mode::Reader, Action::Top) => app.reader_scroll = 0,
+        (Mode::Reader, Action::Bottom) => app.reader_scroll = u16::MAX,
+        (Mode::Reader, Action::Open) => app.open_reader_url(),
+
+        (Mode::Questions, Action::Questions) => app.toggle_questions_focus(),
+        (Mode::Questions, Action::Up) => app.previous_question(),
+        (Mode::Questions, Action::Down) => app.next_question(),
+        (Mode::Questions, Action::Submit) => app.search_question().await?,
+
+        (Mode::Normal, Action::Edit) => {
+            app.display_mode = DisplayMode::Home;
+            app.input_mode = InputMode::Editing;
+        }
+        (Mode::Normal, Action::Debug) => app.toggle_debug_mode(),
+        (Mode::Normal, Action::Notifications) => app.toggle_cache_notification(),
+        (Mode::Normal, Action::Backend) => app.cycle_backend(),
+        (Mode::Normal, Action::More) => app.load_more().await?,
+        (Mode::Normal, Action::Retry) => app.retry_search(),
+        (Mode::Normal, Action::Questions) => app.toggle_questions_focus(),
+        (Mode::Normal, Action::Filters) => app.open_filters(),
+        (Mode::Normal, Action::NextVertical) => app.switch_vertical(app.vertical.next()).await?,
+        (Mode::Normal, Action::PreviousVertical) => {
+            app.switch_vertical(app.vertical.previous()).await?
+        }
+        (Mode::Normal, Action::Proxies) => app.toggle_proxy_status(),
+        (Mode::Normal, Action::Cache) => app.open_cache_panel()?,
+        (Mode::Normal, Action::Cancel) if app.show_proxies => app.toggle_proxy_status(),
+        (Mode::Normal, Action::Cancel) if app.pending_search.is_some() => app.cancel_search(),
+        (Mode::Normal, Action::Up) => app.previous_result(),
+        (Mode::Normal, Action::Down) => app.next_result(),
+        (Mode::Normal, Action::Clear) => app.clear_input(),
+        (Mode::Normal, Action::Open) => app.open_url(),
+        (Mode::Normal, Action::Read) if app.display_mode == DisplayMode::Ready => app.open_reader(),
+
+        (Mode::Filters, Action::Cancel) => app.cancel_filters(),
+        (Mode::Filters, Action::Submit) => app.apply_filters().await?,
+        (Mode::Filters, Action::Up) => app.select_filter(-1),
+        (Mode::Filters, Action::Down) => app.select_filter(1),
+        (Mode::Filters, Action::Cycle) => app.cycle_filter(),
+        (Mode::Filters, Action::Reset) => app.reset_filter(),
+        (Mode::Filters, Action::DeleteChar) => app.filter_delete_char(),
+
+        (Mode::Cache, Action::Up) => app.select_cache_entry(-1),
+        (Mode::Cache, Action::Down) => app.select_cache_entry(1),
+        (Mode::Cache, Action::Refresh) => app.refresh_cache_entry(),
+        (Mode::Cache, Action::Delete) => app.delete_cache_entry()?,
+        (Mode::Cache, Action::Close) => app.close_cache_panel(),
+
+        (Mode::Editing, Action::Submit) => {
+            app.submit().await?;
+            app.input_mode = InputMode::Normal;
+            app.has_entered = true;
+            app.history.index = 0;
+            app.history.show_history_popup = false;
+        }
+        (Mode::Editing, Action::DeleteChar) => {
+            app.delete_char();
+            app.history.index = 0;
+            app.history.show_history_popup = false;
+        }
+        (Mode::Editing, Action::Up) if !app.history.search_history.is_empty() => {
+            app.previous_history();
+            app.set_input_to_history();
+        }
+        (Mode::Editing, Action::Down)
+            if app.history.show_history_popup && !app.history.search_history.is_empty() =>
+        {
+            app.next_history();
+            app.set_input_to_history();
+        }
+        (Mode::Editing, Action::Left) => app.move_cursor_left(),
+        (Mode::Editing, Action::Right) => app.move_cursor_right(),
+        (Mode::Editing, Action::Cancel) => app.exit_input_mode(),
+        _ => {}
     }
     Ok(())
 }
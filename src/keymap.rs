@@ -0,0 +1,629 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{de, Deserializer};
+use serde_derive::Deserialize;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// Something a key can do. Which actions exist depends on the [`Mode`].
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Edit,
+    Clear,
+    Open,
+    Read,
+    More,
+    Retry,
+    Debug,
+    Notifications,
+    Backend,
+    Questions,
+    Filters,
+    Proxies,
+    Cache,
+    NextVertical,
+    PreviousVertical,
+    Cancel,
+    Submit,
+    Close,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Cycle,
+    Reset,
+    DeleteChar,
+    Delete,
+    Refresh,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Edit => "edit",
+            Action::Clear => "clear",
+            Action::Open => "open",
+            Action::Read => "read",
+            Action::More => "more",
+            Action::Debug => "debug",
+            Action::Notifications => "notifications",
+            Action::Backend => "backend",
+            Action::Questions => "questions",
+            Action::Filters => "filters",
+            Action::Retry => "retry",
+            Action::Proxies => "proxies",
+            Action::Cache => "cache",
+            Action::NextVertical => "next_vertical",
+            Action::PreviousVertical => "previous_vertical",
+            Action::Cancel => "cancel",
+            Action::Submit => "submit",
+            Action::Close => "close",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Cycle => "cycle",
+            Action::Reset => "reset",
+            Action::DeleteChar => "delete_char",
+            Action::Delete => "delete",
+            Action::Refresh => "refresh",
+        }
+    }
+}
+
+/// Where the keys are pressed. Normal mode is split by screen because the
+/// same key means different things on each.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Mode {
+    Normal,
+    Questions,
+    Reader,
+    Searching,
+    Filters,
+    Cache,
+    Editing,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 7] = [
+        Mode::Normal,
+        Mode::Questions,
+        Mode::Reader,
+        Mode::Searching,
+        Mode::Filters,
+        Mode::Cache,
+        Mode::Editing,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Questions => "questions",
+            Mode::Reader => "reader",
+            Mode::Searching => "searching",
+            Mode::Filters => "filters",
+            Mode::Cache => "cache",
+            Mode::Editing => "editing",
+        }
+    }
+
+    /// Whether plain characters are typed as text in this mode.
+    pub fn takes_text(self) -> bool {
+        matches!(self, Mode::Filters | Mode::Editing)
+    }
+
+    /// The actions of this mode with their default keys, in help order.
+    fn defaults(self) -> &'static [(Action, &'static [&'static str])] {
+        match self {
+            Mode::Normal => &[
+                (Action::Quit, &["q"]),
+                (Action::Edit, &["e"]),
+                (Action::Clear, &["c"]),
+                (Action::Open, &["o"]),
+                (Action::Read, &["enter"]),
+                (Action::More, &["m"]),
+                (Action::Retry, &["r"]),
+                (Action::Questions, &["p"]),
+                (Action::Filters, &["f"]),
+                (Action::Proxies, &["x"]),
+                (Action::Cache, &["C"]),
+                (Action::NextVertical, &["tab"]),
+                (Action::PreviousVertical, &["shift+tab"]),
+                (Action::Debug, &["d"]),
+                (Action::Notifications, &["n"]),
+                (Action::Backend, &["b"]),
+                (Action::Up, &["up", "k"]),
+                (Action::Down, &["down", "j"]),
+                (Action::Cancel, &["esc"]),
+            ],
+            Mode::Questions => &[
+                (Action::Submit, &["enter"]),
+                (Action::Questions, &["esc", "p"]),
+                (Action::Up, &["up", "k"]),
+                (Action::Down, &["down", "j"]),
+                (Action::Quit, &["q"]),
+            ],
+            Mode::Reader => &[
+                (Action::Close, &["esc", "backspace", "h"]),
+                (Action::Up, &["up", "k"]),
+                (Action::Down, &["down", "j"]),
+                (Action::PageUp, &["pageup"]),
+                (Action::PageDown, &["pagedown", "space"]),
+                (Action::Top, &["g"]),
+                (Action::Bottom, &["G"]),
+                (Action::Open, &["o"]),
+                (Action::Quit, &["q"]),
+            ],
+            Mode::Searching => &[(Action::Cancel, &["esc"]), (Action::Quit, &["q"])],
+            Mode::Filters => &[
+                (Action::Submit, &["enter"]),
+                (Action::Cancel, &["esc"]),
+                (Action::Up, &["up", "shift+tab"]),
+                (Action::Down, &["down", "tab"]),
+                (Action::Cycle, &["left", "right"]),
+                (Action::Reset, &["delete"]),
+                (Action::DeleteChar, &["backspace"]),
+            ],
+            Mode::Cache => &[
+                (Action::Up, &["up", "k"]),
+                (Action::Down, &["down", "j"]),
+                (Action::Refresh, &["r"]),
+                (Action::Delete, &["d", "delete"]),
+                (Action::Close, &["esc", "C"]),
+                (Action::Quit, &["q"]),
+            ],
+            Mode::Editing => &[
+                (Action::Submit, &["enter"]),
+                (Action::Cancel, &["esc"]),
+                (Action::Up, &["up"]),
+                (Action::Down, &["down"]),
+                (Action::Left, &["left"]),
+                (Action::Right, &["right"]),
+                (Action::DeleteChar, &["backspace"]),
+            ],
+        }
+    }
+}
+
+/// A key with its Ctrl/Alt/Shift modifiers, written `ctrl+r`, `alt+enter`,
+/// `shift+tab`, `G` or `pagedown` in the config.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Terminals report Shift alongside the uppercase letter or BackTab it
+    /// already produced; drop it so `G` matches a plain `G` chord.
+    fn from_event(key: &KeyEvent) -> Self {
+        let mut modifiers = key.modifiers;
+        if matches!(key.code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /// A key that types a character, which text inputs must keep.
+    fn is_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_lowercase();
+            let (modifier, len) = if lower.starts_with("ctrl+") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt+") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift+") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            modifiers |= modifier;
+            rest = &rest[len..];
+        }
+
+        // the arrows are written as `Display` shows them too
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some('↑'), None) => KeyCode::Up,
+            (Some('↓'), None) => KeyCode::Down,
+            (Some('←'), None) => KeyCode::Left,
+            (Some('→'), None) => KeyCode::Right,
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+
+        // Shift is part of the character or BackTab, see `from_event`.
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::BackTab => f.write_str("Shift+Tab"),
+            KeyCode::Backspace => f.write_str("Backspace"),
+            KeyCode::Delete => f.write_str("Del"),
+            KeyCode::Insert => f.write_str("Ins"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::Home => f.write_str("Home"),
+            KeyCode::End => f.write_str("End"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for KeyChord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Keys of one action in the config, either `"q"` or `["q", "ctrl+c"]`.
+/// An empty list unbinds the action.
+#[derive(Debug, Clone)]
+pub struct Chords(pub Vec<KeyChord>);
+
+impl<'de> serde::Deserialize<'de> for Chords {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ChordsVisitor;
+
+        impl<'de> de::Visitor<'de> for ChordsVisitor {
+            type Value = Chords;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a key or a list of keys")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Chords, E> {
+                Ok(Chords(vec![s.parse().map_err(E::custom)?]))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Chords, A::Error> {
+                let mut chords = Vec::new();
+                while let Some(chord) = seq.next_element()? {
+                    chords.push(chord);
+                }
+                Ok(Chords(chords))
+            }
+        }
+
+        deserializer.deserialize_any(ChordsVisitor)
+    }
+}
+
+/// The `[keybindings.<mode>]` tables of the config. Actions left out keep
+/// their default keys.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeymapConfig {
+    pub normal: HashMap<Action, Chords>,
+    pub questions: HashMap<Action, Chords>,
+    pub reader: HashMap<Action, Chords>,
+    pub searching: HashMap<Action, Chords>,
+    pub filters: HashMap<Action, Chords>,
+    pub cache: HashMap<Action, Chords>,
+    pub editing: HashMap<Action, Chords>,
+}
+
+impl KeymapConfig {
+    fn mode(&self, mode: Mode) -> &HashMap<Action, Chords> {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Questions => &self.questions,
+            Mode::Reader => &self.reader,
+            Mode::Searching => &self.searching,
+            Mode::Filters => &self.filters,
+            Mode::Cache => &self.cache,
+            Mode::Editing => &self.editing,
+        }
+    }
+}
+
+/// Keys of every action in every mode: the defaults with the config's
+/// overrides applied.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    modes: HashMap<Mode, Vec<(Action, Vec<KeyChord>)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&KeymapConfig::default()).expect("default keymap is valid")
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap, or lists every override that can't be used.
+    pub fn new(config: &KeymapConfig) -> Result<Self, Vec<String>> {
+        let mut problems = Vec::new();
+        let mut modes = HashMap::new();
+        for mode in Mode::ALL {
+            let mut bindings: Vec<(Action, Vec<KeyChord>)> = mode
+                .defaults()
+                .iter()
+                .map(|(action, keys)| {
+                    let chords = keys
+                        .iter()
+                        .map(|key| key.parse().expect("default keys parse"))
+                        .collect();
+                    (*action, chords)
+                })
+                .collect();
+
+            let overrides = config.mode(mode);
+            let mut actions: Vec<&Action> = overrides.keys().collect();
+            actions.sort_by_key(|action| action.name());
+            for action in actions {
+                let chords = &overrides[action].0;
+                let Some(binding) = bindings.iter_mut().find(|(a, _)| a == action) else {
+                    problems.push(format!(
+                        "keybindings.{}.{} is not a {} mode action",
+                        mode.name(),
+                        action.name(),
+                        mode.name()
+                    ));
+                    continue;
+                };
+                if mode.takes_text() {
+                    for chord in chords.iter().filter(|chord| chord.is_text()) {
+                        problems.push(format!(
+                            "keybindings.{}.{} can't use '{}', it is needed for typing",
+                            mode.name(),
+                            action.name(),
+                            chord
+                        ));
+                    }
+                }
+                binding.1 = chords.clone();
+            }
+
+            let mut seen: Vec<(KeyChord, Action)> = Vec::new();
+            for (action, chords) in &bindings {
+                for chord in chords {
+                    match seen.iter().find(|(c, _)| c == chord) {
+                        Some((_, other)) if other != action => problems.push(format!(
+                            "keybindings.{}: '{}' is bound to both {} and {}",
+                            mode.name(),
+                            chord,
+                            other.name(),
+                            action.name()
+                        )),
+                        Some(_) => {}
+                        None => seen.push((*chord, *action)),
+                    }
+                }
+            }
+            modes.insert(mode, bindings);
+        }
+
+        if problems.is_empty() {
+            Ok(Self { modes })
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// The action `key` triggers in `mode`.
+    pub fn action(&self, mode: Mode, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+        self.modes[&mode]
+            .iter()
+            .find(|(_, chords)| chords.contains(&chord))
+            .map(|(action, _)| *action)
+    }
+
+    /// The first key of `action` in `mode`, shown in the help footer.
+    pub fn key(&self, mode: Mode, action: Action) -> Option<KeyChord> {
+        self.modes[&mode]
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, chords)| chords.first().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    fn config(toml: &str) -> KeymapConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn parses_modifiers_in_any_case() {
+        let expected = KeyChord {
+            code: KeyCode::Char('x'),
+            modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT,
+        };
+        assert_eq!(chord("ctrl+alt+x"), expected);
+        assert_eq!(chord("Alt+CTRL+x"), expected);
+        assert_eq!(chord("f5").code, KeyCode::F(5));
+        assert_eq!(chord("space").code, KeyCode::Char(' '));
+        assert_eq!(chord("+").code, KeyCode::Char('+'));
+        assert!("f13".parse::<KeyChord>().is_err());
+        assert!("hyper+x".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn folds_shift_into_the_key() {
+        assert_eq!(chord("shift+a"), chord("A"));
+        assert_eq!(chord("shift+a").modifiers, KeyModifiers::NONE);
+        assert_eq!(chord("shift+tab"), chord("backtab"));
+        assert_eq!(
+            chord("shift+up").modifiers,
+            KeyModifiers::SHIFT,
+            "Shift stays on keys that don't type anything"
+        );
+        // terminals send Shift with the uppercase letter
+        let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from_event(&event), chord("G"));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let keys = [
+            "q",
+            "G",
+            "ctrl+c",
+            "ctrl+alt+x",
+            "alt+enter",
+            "shift+tab",
+            "space",
+            "f12",
+            "esc",
+            "tab",
+            "backspace",
+            "delete",
+            "insert",
+            "up",
+            "down",
+            "left",
+            "right",
+            "home",
+            "end",
+            "pageup",
+            "pagedown",
+            "shift+up",
+            "ctrl+shift+f1",
+        ];
+        for key in keys {
+            let chord = chord(key);
+            assert_eq!(chord.to_string().parse(), Ok(chord), "{} as {}", key, chord);
+        }
+        assert_eq!(chord("ctrl+alt+x").to_string(), "Ctrl+Alt+x");
+        assert_eq!(chord("pagedown").to_string(), "PgDn");
+    }
+
+    #[test]
+    fn overrides_replace_the_defaults() {
+        let keymap = Keymap::new(&config(
+            "[normal]\nquit = [\"ctrl+q\", \"Q\"]\nopen = []\n[editing]\ncancel = \"ctrl+g\"",
+        ))
+        .unwrap();
+        let quit = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(Mode::Normal, &quit), Some(Action::Quit));
+        let q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(Mode::Normal, &q), None);
+        assert_eq!(keymap.key(Mode::Normal, Action::Open), None);
+        assert_eq!(
+            keymap.key(Mode::Editing, Action::Cancel),
+            Some(chord("ctrl+g"))
+        );
+        // other modes keep theirs
+        assert_eq!(keymap.action(Mode::Reader, &q), Some(Action::Quit));
+    }
+
+    #[test]
+    fn rejects_duplicate_chords_in_a_mode() {
+        let problems = Keymap::new(&config("[normal]\nopen = \"q\"")).unwrap_err();
+        assert_eq!(
+            problems,
+            ["keybindings.normal: 'q' is bound to both quit and open"]
+        );
+        // the same key in two modes is fine
+        assert!(Keymap::new(&config("[reader]\nopen = \"m\"")).is_ok());
+    }
+
+    #[test]
+    fn rejects_text_keys_where_typing() {
+        let problems = Keymap::new(&config("[editing]\nsubmit = [\"x\", \"ctrl+x\"]")).unwrap_err();
+        assert_eq!(
+            problems,
+            ["keybindings.editing.submit can't use 'x', it is needed for typing"]
+        );
+        assert!(Keymap::new(&config("[filters]\ncancel = \"space\"")).is_err());
+        assert!(Keymap::new(&config("[normal]\nquit = \"z\"")).is_ok());
+    }
+
+    #[test]
+    fn rejects_actions_of_other_modes() {
+        let problems = Keymap::new(&config("[searching]\nopen = \"o\"")).unwrap_err();
+        assert_eq!(
+            problems,
+            ["keybindings.searching.open is not a searching mode action"]
+        );
+        assert!(toml::from_str::<KeymapConfig>("[normal]\nquit = \"hyper+q\"").is_err());
+    }
+}
@@ -1,10 +1,15 @@
 mod app;
+mod cli;
+mod config;
+mod crawl;
 mod data;
 mod event;
+mod keymap;
 mod search;
 mod ui;
 mod utils;
 
+use clap::Parser;
 use color_eyre::Result;
 use crossterm::{
     execute,
@@ -12,19 +17,37 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, Stdout};
+use std::process::ExitCode;
 
 use crate::app::App;
+use crate::cli::{Cli, Command};
 use crate::event::handle_events;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    utils::setup_panic_hook();
+async fn main() -> Result<ExitCode> {
     color_eyre::install()?;
+    let cli = Cli::parse();
+    let config = match cli.load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(1));
+        }
+    };
+
+    match cli.command {
+        Some(Command::Search(args)) => return cli::run_search(args, &config).await,
+        Some(Command::Cache { command }) => return cli::run_cache(command, &config).await,
+        None => {}
+    }
+
+    utils::setup_panic_hook();
 
     let mut terminal = setup_terminal()?;
-    let mut app = App::new().await?;
+    let mut app = App::new(config).await?;
 
     loop {
+        app.tick().await?;
         terminal.draw(|frame| app.ui(frame))?;
 
         handle_events(&mut app).await?;
@@ -34,8 +57,15 @@ async fn main() -> Result<()> {
         }
     }
 
+    let saved = app.proxies.cookies().save().await;
     restore_terminal()?;
-    Ok(())
+    if let Err(e) = saved {
+        eprintln!("Warning: couldn't save the cookies: {}", e);
+    }
+    for warning in app.proxies.cookies().take_warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(ExitCode::SUCCESS)
 }
 
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
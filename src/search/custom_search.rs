@@ -0,0 +1,515 @@
+use super::filters::{SafeSearch, TimeRange};
+use super::{
+    QueryArgs, QueryResult, SearchBackend, SearchFilters, SearchPage, SerpFeatures, Vertical,
+};
+use crate::config::CustomSearchConfig;
+use crate::crawl::check::quick_check;
+use async_trait::async_trait;
+use color_eyre::Result;
+use serde_derive::Deserialize;
+use std::{collections::HashMap, env, fmt, time::Duration};
+
+const DEFAULT_BASE_URL: &str = "https://www.googleapis.com/customsearch/v1";
+const MAX_NUM: usize = 10;
+
+/// Queries the official Custom Search JSON API.
+///
+/// Needs an API key and a programmable search engine id (`cx`), from the
+/// `[custom_search]` config or else `GOOGLE_API_KEY` and `GOOGLE_CSE_ID`.
+/// `base_url` or `GOOGLE_CSE_BASE_URL` overrides the endpoint, e.g. to point
+/// at a local stub server.
+pub struct CustomSearch {
+    api_key: String,
+    cx: String,
+    base_url: String,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+#[derive(Debug)]
+pub enum CustomSearchError {
+    MissingCredentials,
+    /// HTTP 429: too many requests in a short time.
+    RateLimited(String),
+    /// HTTP 403 caused by the daily quota running out.
+    QuotaExceeded(String),
+    /// Any other HTTP 403, e.g. a bad key or the API not being enabled.
+    AccessDenied(String),
+    Http {
+        status: u16,
+        message: String,
+    },
+    /// The API only searches the web and images.
+    UnsupportedVertical(Vertical),
+}
+
+impl fmt::Display for CustomSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CustomSearchError::MissingCredentials => write!(
+                f,
+                "Custom Search API needs custom_search.api_key and custom_search.cx in the config, \
+                 or GOOGLE_API_KEY and GOOGLE_CSE_ID to be set"
+            ),
+            CustomSearchError::RateLimited(msg) => {
+                write!(f, "Custom Search API rate limit hit: {}", msg)
+            }
+            CustomSearchError::QuotaExceeded(msg) => {
+                write!(f, "Custom Search API daily quota exceeded: {}", msg)
+            }
+            CustomSearchError::AccessDenied(msg) => {
+                write!(f, "Custom Search API access denied: {}", msg)
+            }
+            CustomSearchError::Http { status, message } => {
+                write!(f, "Custom Search API error {}: {}", status, message)
+            }
+            CustomSearchError::UnsupportedVertical(vertical) => write!(
+                f,
+                "Custom Search API can't search {}, switch to the google backend",
+                vertical.name().to_lowercase()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CustomSearchError {}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    link: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    snippet: String,
+    #[serde(default)]
+    display_link: String,
+    #[serde(default)]
+    formatted_url: String,
+    #[serde(default)]
+    pagemap: Option<PageMap>,
+    /// Only set for `searchType=image`, where `link` is the image itself.
+    #[serde(default)]
+    image: Option<ImageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageInfo {
+    #[serde(default)]
+    context_link: String,
+    #[serde(default)]
+    thumbnail_link: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageMap {
+    #[serde(default)]
+    metatags: Vec<HashMap<String, serde_json::Value>>,
+}
+
+impl Item {
+    fn site_name(&self) -> String {
+        self.pagemap
+            .as_ref()
+            .and_then(|p| p.metatags.first())
+            .and_then(|tags| tags.get("og:site_name"))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| self.display_link.trim_start_matches("www.").to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    errors: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    reason: String,
+}
+
+impl CustomSearch {
+    pub fn new(
+        api_key: String,
+        cx: String,
+        base_url: Option<String>,
+        timeout: Duration,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            api_key,
+            cx,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            timeout,
+            client,
+        }
+    }
+
+    /// Takes each setting from `config`, or from its environment variable
+    /// when it's left out or empty.
+    pub fn from_config(
+        config: &CustomSearchConfig,
+        timeout: Duration,
+        client: reqwest::Client,
+    ) -> Result<Self> {
+        let api_key = setting(&config.api_key, "GOOGLE_API_KEY");
+        let cx = setting(&config.cx, "GOOGLE_CSE_ID");
+        match (api_key, cx) {
+            (Some(api_key), Some(cx)) => Ok(Self::new(
+                api_key,
+                cx,
+                setting(&config.base_url, "GOOGLE_CSE_BASE_URL"),
+                timeout,
+                client,
+            )),
+            _ => Err(CustomSearchError::MissingCredentials.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for CustomSearch {
+    fn name(&self) -> &'static str {
+        "custom search api"
+    }
+
+    async fn search(&self, args: &QueryArgs) -> Result<SearchPage> {
+        let search_type = match args.vertical {
+            Vertical::Web => None,
+            Vertical::Images => Some(("searchType", "image")),
+            vertical => return Err(CustomSearchError::UnsupportedVertical(vertical).into()),
+        };
+        // the API serves at most 10 results per request and counts from 1
+        let num = args.num.clamp(1, MAX_NUM);
+        let start = args.page * num + 1;
+        let resp = self
+            .client
+            .get(&self.base_url)
+            .query(&[
+                ("key", self.api_key.as_str()),
+                ("cx", self.cx.as_str()),
+                ("q", args.query.as_str()),
+                ("num", &num.to_string()),
+                ("start", &start.to_string()),
+            ])
+            .query(&filter_params(&args.filters))
+            .query(search_type.as_slice())
+            .timeout(self.timeout)
+            .send()
+            .await
+            // the request url carries the API key, keep it out of error messages
+            .map_err(|e| e.without_url())?;
+
+        let status = resp.status().as_u16();
+        if !resp.status().is_success() {
+            let body = resp.text().await?;
+            return Err(classify_error(status, &body).into());
+        }
+
+        let body: ApiResponse = resp.json().await.map_err(|e| e.without_url())?;
+        let results = body
+            .items
+            .into_iter()
+            .map(|item| {
+                let site_name = item.site_name();
+                // link to the page showing the image rather than the image file
+                let (url, thumbnail) = match item.image {
+                    Some(image) if !image.context_link.is_empty() => {
+                        (image.context_link, image.thumbnail_link)
+                    }
+                    _ => (item.link, String::new()),
+                };
+                QueryResult {
+                    url_supported_flag: quick_check(&url),
+                    site_name,
+                    title: item.title,
+                    display_url: item.formatted_url,
+                    url,
+                    description: item.snippet.replace('\n', " ").trim().to_string(),
+                    thumbnail,
+                    ..Default::default()
+                }
+                .with_fallbacks()
+            })
+            .collect();
+        Ok(SearchPage {
+            results,
+            strategy: None,
+            features: SerpFeatures::default(),
+        })
+    }
+}
+
+fn setting(value: &Option<String>, var: &str) -> Option<String> {
+    let value = value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    match value {
+        Some(value) => Some(value.to_string()),
+        None => env::var(var).ok().filter(|value| !value.is_empty()),
+    }
+}
+
+/// The API takes the filters as parameters rather than query operators.
+fn filter_params(filters: &SearchFilters) -> Vec<(&'static str, String)> {
+    let mut params = Vec::new();
+    if !filters.site().is_empty() {
+        params.push(("siteSearch", filters.site().to_string()));
+    }
+    if !filters.filetype().is_empty() {
+        params.push(("fileType", filters.filetype().to_string()));
+    }
+    // the API has no hour granularity, a day is the closest
+    let date_restrict = match filters.time_range {
+        TimeRange::Any => None,
+        TimeRange::Hour | TimeRange::Day => Some("d1"),
+        TimeRange::Week => Some("w1"),
+        TimeRange::Month => Some("m1"),
+        TimeRange::Year => Some("y1"),
+    };
+    if let Some(value) = date_restrict {
+        params.push(("dateRestrict", value.to_string()));
+    }
+    if !filters.region().is_empty() {
+        params.push(("gl", filters.region().to_string()));
+    }
+    if !filters.language().is_empty() {
+        params.push(("hl", filters.language().to_string()));
+    }
+    match filters.safe_search {
+        SafeSearch::Default => {}
+        SafeSearch::Off => params.push(("safe", "off".to_string())),
+        SafeSearch::Strict => params.push(("safe", "active".to_string())),
+    }
+    params
+}
+
+fn classify_error(status: u16, body: &str) -> CustomSearchError {
+    let (message, reasons) = match serde_json::from_str::<ErrorResponse>(body) {
+        Ok(resp) => (
+            resp.error.message,
+            resp.error
+                .errors
+                .into_iter()
+                .map(|e| e.reason)
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => (body.trim().to_string(), Vec::new()),
+    };
+    let is_quota = reasons
+        .iter()
+        .any(|r| r.contains("dailyLimitExceeded") || r.contains("quotaExceeded"));
+
+    match status {
+        429 => CustomSearchError::RateLimited(message),
+        403 if is_quota => CustomSearchError::QuotaExceeded(message),
+        403 => CustomSearchError::AccessDenied(message),
+        _ => CustomSearchError::Http { status, message },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Answers one request with `status` and `body`, returning the base url
+    /// to search and a handle to the request line it got.
+    async fn serve(status: u16, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/customsearch/v1", listener.local_addr().unwrap());
+        let request = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let request = String::from_utf8_lossy(&request);
+            request.lines().next().unwrap_or_default().to_string()
+        });
+        (base_url, request)
+    }
+
+    async fn search(
+        status: u16,
+        body: &'static str,
+        args: &QueryArgs,
+    ) -> (Result<SearchPage>, String) {
+        let (base_url, request) = serve(status, body).await;
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let backend = CustomSearch::new(
+            "k".to_string(),
+            "c".to_string(),
+            Some(base_url),
+            Duration::from_secs(5),
+            client,
+        );
+        let result = backend.search(args).await;
+        (result, request.await.unwrap())
+    }
+
+    async fn search_error(status: u16, body: &'static str) -> CustomSearchError {
+        let (result, _) = search(status, body, &QueryArgs::default()).await;
+        result
+            .unwrap_err()
+            .downcast::<CustomSearchError>()
+            .expect("a CustomSearchError")
+    }
+
+    const ITEMS: &str = r#"{
+        "kind": "customsearch#search",
+        "items": [
+            {
+                "link": "https://www.rust-lang.org/",
+                "title": "Rust Programming Language",
+                "snippet": "A language empowering everyone\nto build reliable software.",
+                "displayLink": "www.rust-lang.org",
+                "formattedUrl": "https://www.rust-lang.org/",
+                "pagemap": {"metatags": [{"og:site_name": "Rust"}]}
+            },
+            {
+                "link": "https://doc.rust-lang.org/book/",
+                "title": "The Rust Programming Language - The Rust Book",
+                "snippet": "by S Klabnik ",
+                "displayLink": "doc.rust-lang.org",
+                "formattedUrl": "https://doc.rust-lang.org/book/"
+            }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn maps_items_to_results() {
+        let args = QueryArgs {
+            query: "rust lang".to_string(),
+            page: 1,
+            ..Default::default()
+        };
+        let (result, request) = search(200, ITEMS, &args).await;
+        assert!(request.starts_with("GET /customsearch/v1?key=k&cx=c&q=rust+lang&num=10&start=11 "));
+
+        let results = result.unwrap().results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].url, "https://www.rust-lang.org/");
+        assert_eq!(results[0].title, "Rust Programming Language");
+        assert_eq!(
+            results[0].description,
+            "A language empowering everyone to build reliable software."
+        );
+        assert_eq!(results[0].site_name, "Rust");
+        assert_eq!(results[0].display_url, "https://www.rust-lang.org/");
+        assert_eq!(results[1].url, "https://doc.rust-lang.org/book/");
+        assert_eq!(
+            results[1].title,
+            "The Rust Programming Language - The Rust Book"
+        );
+        assert_eq!(results[1].description, "by S Klabnik");
+        assert_eq!(results[1].site_name, "doc.rust-lang.org");
+    }
+
+    #[tokio::test]
+    async fn reads_no_items_as_no_results() {
+        let (result, _) = search(
+            200,
+            r#"{"kind": "customsearch#search"}"#,
+            &QueryArgs::default(),
+        )
+        .await;
+        assert!(result.unwrap().results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sorts_out_error_answers() {
+        let err = search_error(
+            429,
+            r#"{"error": {"code": 429, "message": "Rate Limit Exceeded",
+                "errors": [{"reason": "rateLimitExceeded"}]}}"#,
+        )
+        .await;
+        assert!(
+            matches!(&err, CustomSearchError::RateLimited(m) if m == "Rate Limit Exceeded"),
+            "{:?}",
+            err
+        );
+
+        let err = search_error(
+            403,
+            r#"{"error": {"code": 403, "message": "Daily Limit Exceeded",
+                "errors": [{"reason": "dailyLimitExceeded"}]}}"#,
+        )
+        .await;
+        assert!(
+            matches!(&err, CustomSearchError::QuotaExceeded(m) if m == "Daily Limit Exceeded"),
+            "{:?}",
+            err
+        );
+
+        let err = search_error(
+            403,
+            r#"{"error": {"code": 403, "message": "Requests from this client are blocked.",
+                "errors": [{"reason": "forbidden"}]}}"#,
+        )
+        .await;
+        assert!(
+            matches!(&err, CustomSearchError::AccessDenied(m) if m == "Requests from this client are blocked."),
+            "{:?}",
+            err
+        );
+
+        let err = search_error(502, "Bad Gateway\n").await;
+        assert!(
+            matches!(&err, CustomSearchError::Http { status: 502, message } if message == "Bad Gateway"),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn prefers_the_config_to_the_environment() {
+        let var = "CMDGOOGLE_TEST_CUSTOM_SEARCH_SETTING";
+        env::set_var(var, "from env");
+        assert_eq!(
+            setting(&Some(" from config ".to_string()), var).as_deref(),
+            Some("from config")
+        );
+        assert_eq!(setting(&None, var).as_deref(), Some("from env"));
+        assert_eq!(
+            setting(&Some(String::new()), var).as_deref(),
+            Some("from env")
+        );
+        env::remove_var(var);
+        assert_eq!(setting(&None, var), None);
+    }
+}
@@ -0,0 +1,180 @@
+use super::custom_search::CustomSearchError;
+use super::parser::ParseError;
+use super::{Blocked, HttpError};
+use color_eyre::Report;
+use std::fmt;
+
+/// Why a search failed, sorted by what the user can do about it.
+#[derive(Debug)]
+pub enum SearchError {
+    /// No answer within the search timeout.
+    Timeout,
+    /// The host or proxy couldn't be resolved or connected to.
+    Offline(String),
+    /// Any non-success HTTP status that isn't a block.
+    Http {
+        status: u16,
+        message: String,
+    },
+    /// Google refused every attempt. `fallback` is the fallback backend's
+    /// name and error when it was tried and failed too.
+    Blocked {
+        blocked: Blocked,
+        fallback: Option<(&'static str, String)>,
+    },
+    /// The API's rate limit or daily quota ran out.
+    RateLimited(String),
+    /// The response couldn't be understood.
+    Parse(String),
+    /// The backend can't run this search as configured, e.g. without API
+    /// credentials.
+    Unsupported(String),
+    Other(String),
+}
+
+/// What the error popup suggests doing next.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Remedy {
+    Retry,
+    SwitchBackend,
+    CheckConnection,
+}
+
+impl SearchError {
+    /// Sorts a backend error by the first cause that says what went wrong.
+    pub fn from_report(err: &Report) -> Self {
+        for cause in err.chain() {
+            if let Some(blocked) = cause.downcast_ref::<Blocked>() {
+                return SearchError::Blocked {
+                    blocked: blocked.clone(),
+                    fallback: None,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<HttpError>() {
+                // the body is a whole HTML page, the reason phrase reads better
+                return SearchError::Http {
+                    status: e.status,
+                    message: reqwest::StatusCode::from_u16(e.status)
+                        .ok()
+                        .and_then(|status| status.canonical_reason())
+                        .unwrap_or_default()
+                        .to_string(),
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<CustomSearchError>() {
+                return match e {
+                    CustomSearchError::MissingCredentials
+                    | CustomSearchError::UnsupportedVertical(_) => {
+                        SearchError::Unsupported(e.to_string())
+                    }
+                    CustomSearchError::RateLimited(_) | CustomSearchError::QuotaExceeded(_) => {
+                        SearchError::RateLimited(e.to_string())
+                    }
+                    CustomSearchError::AccessDenied(message) => SearchError::Http {
+                        status: 403,
+                        message: message.clone(),
+                    },
+                    CustomSearchError::Http { status, message } => SearchError::Http {
+                        status: *status,
+                        message: message.clone(),
+                    },
+                };
+            }
+            if cause.downcast_ref::<ParseError>().is_some()
+                || cause.downcast_ref::<serde_json::Error>().is_some()
+            {
+                return SearchError::Parse(cause.to_string());
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return if e.is_timeout() {
+                    SearchError::Timeout
+                } else if e.is_connect() {
+                    SearchError::Offline(
+                        e.url()
+                            .and_then(|url| url.host_str())
+                            .unwrap_or("the server")
+                            .to_string(),
+                    )
+                } else if e.is_decode() {
+                    SearchError::Parse(e.to_string())
+                } else {
+                    SearchError::Other(e.to_string())
+                };
+            }
+        }
+        SearchError::Other(err.to_string())
+    }
+
+    /// Whether trying again soon may work: a timeout or a server error.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SearchError::Timeout => true,
+            SearchError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Popup title.
+    pub fn title(&self) -> &'static str {
+        match self {
+            SearchError::Timeout => "Timed out",
+            SearchError::Offline(_) => "Offline",
+            SearchError::Http { .. } => "HTTP error",
+            SearchError::Blocked { .. } => "Blocked",
+            SearchError::RateLimited(_) => "Rate limited",
+            SearchError::Parse(_) => "Unreadable results",
+            SearchError::Unsupported(_) => "Backend unavailable",
+            SearchError::Other(_) => "Error",
+        }
+    }
+
+    pub fn remedy(&self) -> Remedy {
+        match self {
+            SearchError::Offline(_) => Remedy::CheckConnection,
+            SearchError::Blocked { .. }
+            | SearchError::RateLimited(_)
+            | SearchError::Parse(_)
+            | SearchError::Unsupported(_) => Remedy::SwitchBackend,
+            SearchError::Http { status, .. } if (400..500).contains(status) => {
+                Remedy::SwitchBackend
+            }
+            SearchError::Timeout | SearchError::Http { .. } | SearchError::Other(_) => {
+                Remedy::Retry
+            }
+        }
+    }
+}
+
+impl From<Report> for SearchError {
+    fn from(err: Report) -> Self {
+        Self::from_report(&err)
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::Timeout => write!(f, "The search took longer than the timeout"),
+            SearchError::Offline(host) => write!(f, "Couldn't connect to {}", host),
+            SearchError::Http { status, message } if message.trim().is_empty() => {
+                write!(f, "The search failed with HTTP {}", status)
+            }
+            SearchError::Http { status, message } => {
+                write!(f, "The search failed with HTTP {}: {}", status, message)
+            }
+            SearchError::Blocked { blocked, fallback } => {
+                write!(f, "{}", blocked)?;
+                if let Some((name, error)) = fallback {
+                    write!(f, "; {} also failed: {}", name, error)?;
+                }
+                Ok(())
+            }
+            SearchError::RateLimited(message)
+            | SearchError::Parse(message)
+            | SearchError::Unsupported(message)
+            | SearchError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
@@ -0,0 +1,162 @@
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde_derive::{Deserialize, Serialize};
+
+/// Answer boxes Google shows around the organic results. Cached with
+/// bincode, so fields must not be skipped when serializing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerpFeatures {
+    pub featured_snippet: Option<FeaturedSnippet>,
+    pub knowledge_panel: Option<KnowledgePanel>,
+    /// "People also ask" questions, in page order.
+    pub people_also_ask: Vec<String>,
+}
+
+impl SerpFeatures {
+    pub fn is_empty(&self) -> bool {
+        self.featured_snippet.is_none()
+            && self.knowledge_panel.is_none()
+            && self.people_also_ask.is_empty()
+    }
+}
+
+/// The answer box shown above the results, either a quoted snippet from a
+/// page or a direct answer such as a conversion.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeaturedSnippet {
+    pub text: String,
+    pub source_title: String,
+    pub source_url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgePanel {
+    pub title: String,
+    pub subtitle: String,
+    pub description: String,
+    /// Label/value rows such as `Born` / `June 28, 1971`.
+    pub facts: Vec<(String, String)>,
+}
+
+/// Also skipped when collecting organic results, as the snippet's source
+/// link looks like one.
+pub(super) const SNIPPET_CONTAINER: &str = "block-component, div.xpdopen, div.ifM9O";
+const SNIPPET_TEXT: &str = r#"span.hgKElc, div.Z0LcW, div.IZ6rdc, div[data-attrid="wa:/description"], div[data-tts="answers"]"#;
+const PANEL_CONTAINER: &str = "div.kp-wholepage, div.knowledge-panel, #rhs";
+const PANEL_TITLE: &str = r#"div[data-attrid="title"], h2.qrShPb"#;
+const PANEL_SUBTITLE: &str = r#"div[data-attrid="subtitle"]"#;
+const PANEL_DESCRIPTION: &str = "div.kno-rdesc span";
+const PANEL_FACT: &str = "div.rVusze";
+const FACT_LABEL: &str = "span.w8qArf";
+const FACT_VALUE: &str = "span.LrzXr";
+const QUESTION_PAIR: &str = "div.related-question-pair";
+const QUESTION_TEXT: &str = "div.JlqpRe span, span.CSkcDe";
+
+pub fn parse_features(doc: &Html) -> SerpFeatures {
+    SerpFeatures {
+        featured_snippet: parse_featured_snippet(doc),
+        knowledge_panel: parse_knowledge_panel(doc),
+        people_also_ask: parse_people_also_ask(doc),
+    }
+}
+
+fn parse_featured_snippet(doc: &Html) -> Option<FeaturedSnippet> {
+    let text_sel = selector(SNIPPET_TEXT)?;
+    let title_sel = selector("h3")?;
+    let link_sel = selector("a[href]")?;
+
+    for container in doc.select(&selector(SNIPPET_CONTAINER)?) {
+        let Some(text) = container
+            .select(&text_sel)
+            .map(|e| text_of(&e))
+            .find(|t| !t.is_empty())
+        else {
+            continue;
+        };
+        let source = container.select(&link_sel).find(|a| {
+            a.select(&title_sel).next().is_some()
+                && a.value()
+                    .attr("href")
+                    .is_some_and(|h| h.starts_with("http"))
+        });
+        return Some(FeaturedSnippet {
+            text,
+            source_title: source
+                .and_then(|a| a.select(&title_sel).next())
+                .map(|h| text_of(&h))
+                .unwrap_or_default(),
+            source_url: source
+                .and_then(|a| a.value().attr("href"))
+                .and_then(|h| Url::parse(h).ok())
+                .map(|u| u.to_string())
+                .unwrap_or_default(),
+        });
+    }
+    None
+}
+
+fn parse_knowledge_panel(doc: &Html) -> Option<KnowledgePanel> {
+    let container = doc.select(&selector(PANEL_CONTAINER)?).next()?;
+    let title = first_text(&container, PANEL_TITLE);
+    if title.is_empty() {
+        return None;
+    }
+
+    let label_sel = selector(FACT_LABEL)?;
+    let value_sel = selector(FACT_VALUE)?;
+    let facts = container
+        .select(&selector(PANEL_FACT)?)
+        .filter_map(|row| {
+            let label = text_of(&row.select(&label_sel).next()?);
+            let value = text_of(&row.select(&value_sel).next()?);
+            let label = label.trim_end_matches(':').trim().to_string();
+            (!label.is_empty() && !value.is_empty()).then_some((label, value))
+        })
+        .collect();
+
+    Some(KnowledgePanel {
+        title,
+        subtitle: first_text(&container, PANEL_SUBTITLE),
+        description: first_text(&container, PANEL_DESCRIPTION),
+        facts,
+    })
+}
+
+fn parse_people_also_ask(doc: &Html) -> Vec<String> {
+    let (Some(pair_sel), Some(text_sel)) = (selector(QUESTION_PAIR), selector(QUESTION_TEXT))
+    else {
+        return Vec::new();
+    };
+    let mut questions: Vec<String> = Vec::new();
+    for pair in doc.select(&pair_sel) {
+        let question = pair
+            .value()
+            .attr("data-q")
+            .map(normalize)
+            .or_else(|| pair.select(&text_sel).next().map(|e| text_of(&e)))
+            .unwrap_or_default();
+        if !question.is_empty() && !questions.contains(&question) {
+            questions.push(question);
+        }
+    }
+    questions
+}
+
+fn first_text(element: &ElementRef, sel: &str) -> String {
+    selector(sel)
+        .and_then(|sel| element.select(&sel).next())
+        .map(|e| text_of(&e))
+        .unwrap_or_default()
+}
+
+fn text_of(element: &ElementRef) -> String {
+    normalize(&element.text().collect::<String>())
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn selector(selector: &str) -> Option<Selector> {
+    Selector::parse(selector).ok()
+}
@@ -0,0 +1,344 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// How recent results must be, sent as `tbs=qdr:<unit>`.
+#[derive(PartialEq, Debug, Clone, Copy, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeRange {
+    #[default]
+    Any,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeRange {
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeRange::Any => "any time",
+            TimeRange::Hour => "past hour",
+            TimeRange::Day => "past day",
+            TimeRange::Week => "past week",
+            TimeRange::Month => "past month",
+            TimeRange::Year => "past year",
+        }
+    }
+
+    /// Unit for Google's `qdr:` parameter.
+    pub fn qdr(self) -> Option<&'static str> {
+        match self {
+            TimeRange::Any => None,
+            TimeRange::Hour => Some("h"),
+            TimeRange::Day => Some("d"),
+            TimeRange::Week => Some("w"),
+            TimeRange::Month => Some("m"),
+            TimeRange::Year => Some("y"),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TimeRange::Any => TimeRange::Hour,
+            TimeRange::Hour => TimeRange::Day,
+            TimeRange::Day => TimeRange::Week,
+            TimeRange::Week => TimeRange::Month,
+            TimeRange::Month => TimeRange::Year,
+            TimeRange::Year => TimeRange::Any,
+        }
+    }
+}
+
+/// `Default` leaves the choice to Google, which filters explicit images.
+#[derive(PartialEq, Debug, Clone, Copy, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SafeSearch {
+    #[default]
+    Default,
+    Off,
+    Strict,
+}
+
+impl SafeSearch {
+    pub fn name(self) -> &'static str {
+        match self {
+            SafeSearch::Default => "default",
+            SafeSearch::Off => "off",
+            SafeSearch::Strict => "strict",
+        }
+    }
+
+    /// Value of Google's `safe` parameter.
+    pub fn param(self) -> Option<&'static str> {
+        match self {
+            SafeSearch::Default => None,
+            SafeSearch::Off => Some("off"),
+            SafeSearch::Strict => Some("active"),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SafeSearch::Default => SafeSearch::Off,
+            SafeSearch::Off => SafeSearch::Strict,
+            SafeSearch::Strict => SafeSearch::Default,
+        }
+    }
+}
+
+/// Restrictions applied on top of the query text. Empty strings mean the
+/// filter is off.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchFilters {
+    /// Domain for the `site:` operator.
+    pub site: String,
+    /// Extension for the `filetype:` operator, without the dot.
+    pub filetype: String,
+    pub time_range: TimeRange,
+    /// Two letter country code sent as `gl`.
+    pub region: String,
+    /// Interface language sent as `hl`.
+    pub language: String,
+    pub safe_search: SafeSearch,
+}
+
+impl SearchFilters {
+    /// Whether no filter is on. Blank text fields count as off, as they
+    /// add nothing to the query or the cache key.
+    pub fn is_empty(&self) -> bool {
+        self.site().is_empty()
+            && self.filetype().is_empty()
+            && self.region().is_empty()
+            && self.language().is_empty()
+            && self.time_range == TimeRange::default()
+            && self.safe_search == SafeSearch::default()
+    }
+
+    pub fn site(&self) -> &str {
+        self.site.trim()
+    }
+
+    pub fn filetype(&self) -> &str {
+        self.filetype.trim().trim_start_matches('.')
+    }
+
+    pub fn region(&self) -> &str {
+        self.region.trim()
+    }
+
+    pub fn language(&self) -> &str {
+        self.language.trim()
+    }
+
+    /// `query` with the `site:` and `filetype:` operators appended.
+    pub fn apply_to_query(&self, query: &str) -> String {
+        let mut q = query.to_string();
+        if !self.site().is_empty() {
+            q.push_str(&format!(" site:{}", self.site()));
+        }
+        if !self.filetype().is_empty() {
+            q.push_str(&format!(" filetype:{}", self.filetype()));
+        }
+        q
+    }
+
+    /// Extra Google query parameters, not yet url encoded.
+    pub fn url_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(unit) = self.time_range.qdr() {
+            params.push(("tbs", format!("qdr:{}", unit)));
+        }
+        if !self.region().is_empty() {
+            params.push(("gl", self.region().to_string()));
+        }
+        if !self.language().is_empty() {
+            params.push(("hl", self.language().to_string()));
+        }
+        if let Some(safe) = self.safe_search.param() {
+            params.push(("safe", safe.to_string()));
+        }
+        params
+    }
+
+    /// Every active filter as `name=value` pairs in a fixed order, used in
+    /// cache keys.
+    pub fn key_parts(&self) -> Vec<String> {
+        let mut parts = Vec::new();
+        if !self.site().is_empty() {
+            parts.push(format!("site={}", self.site()));
+        }
+        if !self.filetype().is_empty() {
+            parts.push(format!("filetype={}", self.filetype()));
+        }
+        parts.extend(
+            self.url_params()
+                .into_iter()
+                .map(|(name, value)| format!("{}={}", name, value)),
+        );
+        parts
+    }
+
+    pub fn value(&self, field: FilterField) -> String {
+        match field {
+            FilterField::Site => self.site.clone(),
+            FilterField::Filetype => self.filetype.clone(),
+            FilterField::TimeRange => self.time_range.name().to_string(),
+            FilterField::Region => self.region.clone(),
+            FilterField::Language => self.language.clone(),
+            FilterField::SafeSearch => self.safe_search.name().to_string(),
+        }
+    }
+
+    /// The text behind `field`, or `None` for fields picked from a list.
+    pub fn text_mut(&mut self, field: FilterField) -> Option<&mut String> {
+        match field {
+            FilterField::Site => Some(&mut self.site),
+            FilterField::Filetype => Some(&mut self.filetype),
+            FilterField::Region => Some(&mut self.region),
+            FilterField::Language => Some(&mut self.language),
+            FilterField::TimeRange | FilterField::SafeSearch => None,
+        }
+    }
+
+    /// Steps a list field to its next value.
+    pub fn cycle(&mut self, field: FilterField) {
+        match field {
+            FilterField::TimeRange => self.time_range = self.time_range.next(),
+            FilterField::SafeSearch => self.safe_search = self.safe_search.next(),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for SearchFilters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.site().is_empty() {
+            parts.push(format!("site:{}", self.site()));
+        }
+        if !self.filetype().is_empty() {
+            parts.push(format!("filetype:{}", self.filetype()));
+        }
+        if self.time_range != TimeRange::Any {
+            parts.push(self.time_range.name().to_string());
+        }
+        if !self.region().is_empty() {
+            parts.push(format!("region:{}", self.region()));
+        }
+        if !self.language().is_empty() {
+            parts.push(format!("lang:{}", self.language()));
+        }
+        if self.safe_search != SafeSearch::Default {
+            parts.push(format!("safe:{}", self.safe_search.name()));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+/// Rows of the filter panel.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FilterField {
+    Site,
+    Filetype,
+    TimeRange,
+    Region,
+    Language,
+    SafeSearch,
+}
+
+impl FilterField {
+    pub const ALL: [FilterField; 6] = [
+        FilterField::Site,
+        FilterField::Filetype,
+        FilterField::TimeRange,
+        FilterField::Region,
+        FilterField::Language,
+        FilterField::SafeSearch,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterField::Site => "Site",
+            FilterField::Filetype => "File type",
+            FilterField::TimeRange => "Time range",
+            FilterField::Region => "Region (gl)",
+            FilterField::Language => "Language (hl)",
+            FilterField::SafeSearch => "Safe search",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_filters() -> SearchFilters {
+        SearchFilters {
+            site: " docs.rs ".to_string(),
+            filetype: ".pdf".to_string(),
+            time_range: TimeRange::Week,
+            region: "de".to_string(),
+            language: " en".to_string(),
+            safe_search: SafeSearch::Strict,
+        }
+    }
+
+    #[test]
+    fn pins_the_encoding() {
+        // these strings are cache keys: changing them orphans cached pages
+        let filters = all_filters();
+        assert_eq!(
+            filters.key_parts(),
+            [
+                "site=docs.rs",
+                "filetype=pdf",
+                "tbs=qdr:w",
+                "gl=de",
+                "hl=en",
+                "safe=active"
+            ]
+        );
+        assert_eq!(
+            filters.url_params(),
+            [
+                ("tbs", "qdr:w".to_string()),
+                ("gl", "de".to_string()),
+                ("hl", "en".to_string()),
+                ("safe", "active".to_string()),
+            ]
+        );
+        assert_eq!(
+            filters.apply_to_query("tokio"),
+            "tokio site:docs.rs filetype:pdf"
+        );
+        let off = SearchFilters {
+            safe_search: SafeSearch::Off,
+            time_range: TimeRange::Hour,
+            ..Default::default()
+        };
+        assert_eq!(off.key_parts(), ["tbs=qdr:h", "safe=off"]);
+    }
+
+    #[test]
+    fn blank_fields_are_off() {
+        let blank = SearchFilters {
+            site: "  ".to_string(),
+            filetype: " .".to_string(),
+            region: " ".to_string(),
+            language: "\t".to_string(),
+            ..Default::default()
+        };
+        assert!(blank.is_empty());
+        assert!(blank.key_parts().is_empty());
+        assert_eq!(blank.apply_to_query("rust"), "rust");
+        assert!(SearchFilters::default().is_empty());
+        assert!(!all_filters().is_empty());
+        let week = SearchFilters {
+            time_range: TimeRange::Week,
+            ..Default::default()
+        };
+        assert!(!week.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use super::parser::parse_search_results;
+use super::proxy::{ProxyOutcome, ProxyPool};
+use super::{Blocked, HttpError, QueryArgs, SearchBackend, SearchPage, RESULTS_PER_PAGE};
+use crate::config::Config;
+use async_trait::async_trait;
+use color_eyre::Result;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use urlencoding::encode;
+
+/// Scrapes the regular `google.com/search` HTML page.
+pub struct GoogleScraper {
+    base_url: String,
+    timeout: Duration,
+    user_agents: Vec<String>,
+    proxies: Arc<ProxyPool>,
+}
+
+impl GoogleScraper {
+    pub fn new(config: &Config, proxies: Arc<ProxyPool>) -> Self {
+        Self {
+            base_url: "https://www.google.com/search".to_string(),
+            timeout: config.timeouts.search(),
+            user_agents: config
+                .user_agents
+                .iter()
+                .filter(|ua| !ua.trim().is_empty())
+                .cloned()
+                .collect(),
+            proxies,
+        }
+    }
+
+    async fn send(&self, client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
+        let user_agent = self
+            .user_agents
+            .choose(&mut thread_rng())
+            .map(String::as_str)
+            .unwrap_or_default();
+        let resp = client
+            .get(url)
+            .header("User-Agent", user_agent)
+            .header("Accept-Language", "en-US,en;q=0.9")
+            .header("Referer", "https://www.google.com/")
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        Ok(resp)
+    }
+
+    /// Fetches a results page. When Google redirects to its cookie consent
+    /// page the consent is answered in the shared jar and the request sent
+    /// once more.
+    async fn fetch(&self, client: &reqwest::Client, url: &str) -> Result<String> {
+        let mut resp = self.send(client, url).await?;
+        if is_consent_page(resp.url()) {
+            self.proxies.cookies().accept_google_consent()?;
+            resp = self.send(client, url).await?;
+            if is_consent_page(resp.url()) {
+                return Err(color_eyre::eyre::eyre!(
+                    "Google keeps asking for cookie consent at {}",
+                    resp.url()
+                ));
+            }
+        }
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let redirected_to_sorry = resp.url().path().starts_with("/sorry/");
+        let body = resp.text().await?;
+        let captcha = redirected_to_sorry || is_blocked_page(&body);
+        if captcha || status.as_u16() == 429 {
+            return Err(Blocked {
+                captcha,
+                retry_after,
+                attempts: 1,
+            }
+            .into());
+        }
+        if !status.is_success() {
+            return Err(HttpError {
+                status: status.as_u16(),
+                body,
+            }
+            .into());
+        }
+        Ok(body)
+    }
+}
+
+/// Whether a request ended on Google's cookie consent interstitial, as it
+/// does for EU visitors without a consent cookie.
+fn is_consent_page(url: &reqwest::Url) -> bool {
+    url.host_str()
+        .is_some_and(|host| host.starts_with("consent."))
+}
+
+/// Whether `body` is Google's "unusual traffic" CAPTCHA interstitial
+/// instead of results.
+pub fn is_blocked_page(body: &str) -> bool {
+    body.contains("id=\"captcha-form\"")
+        || body.contains("/sorry/index")
+        || body.contains("detected unusual traffic from your computer network")
+}
+
+#[async_trait]
+impl SearchBackend for GoogleScraper {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    async fn search(&self, args: &QueryArgs) -> Result<SearchPage> {
+        let query = args.filters.apply_to_query(&args.query);
+        let mut search_url = format!("{}?q={}", self.base_url, encode(&query));
+        if args.page > 0 {
+            search_url.push_str(&format!("&start={}", args.start()));
+        }
+        if args.num != RESULTS_PER_PAGE {
+            search_url.push_str(&format!("&num={}", args.num));
+        }
+        if let Some(tbm) = args.vertical.tbm() {
+            search_url.push_str(&format!("&tbm={}", tbm));
+        }
+        for (name, value) in args.filters.url_params() {
+            search_url.push_str(&format!("&{}={}", name, encode(&value)));
+        }
+        let lease = self.proxies.acquire();
+        let result = self.fetch(&lease.client, &search_url).await;
+        if let Some(outcome) = ProxyOutcome::of(&result) {
+            self.proxies.report(&lease, outcome);
+        }
+        let body = result?;
+        if args.debug_mode {
+            let mut file = fs::File::create("debug_raw.html")?;
+            std::io::Write::write_all(&mut file, body.as_bytes())?;
+        }
+        let parsed = parse_search_results(&body, args.vertical)?;
+        Ok(SearchPage {
+            results: parsed.results,
+            strategy: Some(parsed.strategy),
+            features: parsed.features,
+        })
+    }
+}
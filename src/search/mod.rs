@@ -0,0 +1,766 @@
+pub mod custom_search;
+pub mod error;
+pub mod features;
+pub mod filters;
+pub mod google;
+pub mod parser;
+pub mod proxy;
+pub mod retry;
+pub mod vertical;
+
+use crate::config::Config;
+use async_trait::async_trait;
+use color_eyre::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use custom_search::CustomSearch;
+pub use error::SearchError;
+pub use features::SerpFeatures;
+pub use filters::SearchFilters;
+pub use google::GoogleScraper;
+pub use parser::ParseStrategy;
+pub use proxy::ProxyPool;
+pub use retry::RetryPolicy;
+pub use vertical::Vertical;
+
+pub const RESULTS_PER_PAGE: usize = 10;
+
+/// Separates the query from the page, vertical and filters in a cache key.
+pub const CACHE_KEY_SEPARATOR: char = '\u{1f}';
+
+#[derive(Debug, Clone)]
+pub struct QueryArgs {
+    pub query: String,
+    pub debug_mode: bool,
+    /// Zero based result page.
+    pub page: usize,
+    /// Results requested per page.
+    pub num: usize,
+    pub filters: SearchFilters,
+    pub vertical: Vertical,
+}
+
+impl Default for QueryArgs {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            debug_mode: false,
+            page: 0,
+            num: RESULTS_PER_PAGE,
+            filters: SearchFilters::default(),
+            vertical: Vertical::Web,
+        }
+    }
+}
+
+impl QueryArgs {
+    /// Offset of the first result on the requested page.
+    pub fn start(&self) -> usize {
+        self.page * self.num
+    }
+
+    /// Key under which this page of results from `backend` is cached. The
+    /// first unfiltered page of scraped web results with the default page
+    /// size keeps the bare query so existing entries stay valid, and only
+    /// API results name their backend.
+    pub fn cache_key(&self, backend: BackendKind) -> String {
+        let mut parts = Vec::new();
+        if backend != BackendKind::Google {
+            parts.push(format!("backend={}", backend.name()));
+        }
+        if self.page != 0 || self.num != RESULTS_PER_PAGE {
+            parts.push(format!("page={}&num={}", self.page, self.num));
+        }
+        if let Some(tbm) = self.vertical.tbm() {
+            parts.push(format!("tbm={}", tbm));
+        }
+        parts.extend(self.filters.key_parts());
+        if parts.is_empty() {
+            self.query.clone()
+        } else {
+            format!("{}{}{}", self.query, CACHE_KEY_SEPARATOR, parts.join("&"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub url: String,
+    pub title: String,
+    /// The breadcrumb shown under the title, e.g. `docs.rs › tokio`.
+    pub display_url: String,
+    pub site_name: String,
+    pub description: String,
+    pub url_supported_flag: bool,
+    /// Publisher, channel or author, for vertical results.
+    #[serde(default)]
+    pub source: String,
+    /// Publication date as shown by Google, e.g. `3 days ago`.
+    #[serde(default)]
+    pub published: String,
+    #[serde(default)]
+    pub thumbnail: String,
+    /// Video length, e.g. `12:04`.
+    #[serde(default)]
+    pub duration: String,
+}
+
+impl QueryResult {
+    /// Fills in whatever the source page didn't provide from the url itself.
+    pub fn with_fallbacks(mut self) -> Self {
+        if self.site_name.is_empty() {
+            self.site_name = host_of(&self.url);
+        }
+        if self.display_url.is_empty() {
+            self.display_url = self
+                .url
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_end_matches('/')
+                .to_string();
+        }
+        if self.title.is_empty() {
+            self.title = self.display_url.clone();
+        }
+        self
+    }
+
+    /// Source, date, duration and thumbnail of a vertical result, whichever
+    /// are known, in display order.
+    pub fn details(&self) -> Vec<&str> {
+        [
+            &self.source,
+            &self.published,
+            &self.duration,
+            &self.thumbnail,
+        ]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .map(String::as_str)
+        .collect()
+    }
+}
+
+/// Host of `url` without a leading `www.`, or an empty string.
+pub fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| {
+            u.host_str()
+                .map(|h| h.trim_start_matches("www.").to_string())
+        })
+        .unwrap_or_default()
+}
+
+impl fmt::Display for QueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Title: {}\n Url: {}\n Site: {}\n Description: {}\n Crawl supported: {}",
+            self.title, self.url, self.site_name, self.description, self.url_supported_flag
+        )
+    }
+}
+
+/// How a fresh list of results differs from the one it replaces, by url.
+#[derive(Debug, Clone, Default)]
+pub struct ResultsDiff {
+    /// Urls that weren't in the old list.
+    pub added: HashSet<String>,
+    pub removed: usize,
+    /// Results in both lists at a different position.
+    pub moved: usize,
+}
+
+impl ResultsDiff {
+    /// A url listed more than once counts at its first position only.
+    pub fn between(old: &[QueryResult], new: &[QueryResult]) -> Self {
+        let old = unique_urls(old);
+        let new = unique_urls(new);
+        let old_positions: HashMap<&str, usize> =
+            old.iter().enumerate().map(|(i, url)| (*url, i)).collect();
+        let new_urls: HashSet<&str> = new.iter().copied().collect();
+        let mut diff = Self {
+            removed: old.iter().filter(|url| !new_urls.contains(*url)).count(),
+            ..Default::default()
+        };
+        for (i, url) in new.iter().enumerate() {
+            match old_positions.get(url) {
+                Some(&old_i) if old_i != i => diff.moved += 1,
+                Some(_) => {}
+                None => {
+                    diff.added.insert(url.to_string());
+                }
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed == 0 && self.moved == 0
+    }
+}
+
+fn unique_urls(results: &[QueryResult]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    results
+        .iter()
+        .map(|result| result.url.as_str())
+        .filter(|url| seen.insert(*url))
+        .collect()
+}
+
+impl fmt::Display for ResultsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no changes");
+        }
+        let counts = [
+            (self.added.len(), "new"),
+            (self.removed, "gone"),
+            (self.moved, "moved"),
+        ];
+        let parts: Vec<String> = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect();
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// One page of results as returned by a backend.
+#[derive(Debug, Clone)]
+pub struct SearchPage {
+    pub results: Vec<QueryResult>,
+    /// Which HTML layout the results were parsed from, for scraping backends.
+    pub strategy: Option<ParseStrategy>,
+    /// Featured snippet, knowledge panel and related questions, if any.
+    pub features: SerpFeatures,
+}
+
+/// A non-success HTTP status returned by a search endpoint.
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: u16,
+    pub body: String,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HTTP Error: {} - {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Google refused the search as "unusual traffic", with a 429 or its
+/// CAPTCHA page.
+#[derive(Debug, Clone)]
+pub struct Blocked {
+    /// Whether the CAPTCHA page was served rather than a bare 429.
+    pub captcha: bool,
+    /// How long Google asked us to wait, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+    /// Searches made before giving up.
+    pub attempts: u32,
+}
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Google blocked the search as unusual traffic ({})",
+            if self.captcha {
+                "CAPTCHA page"
+            } else {
+                "HTTP 429"
+            }
+        )?;
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+/// A source of search results. Implementations are shared between the UI
+/// and spawned tasks, so they must be `Send + Sync`.
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Short human readable name shown in the UI.
+    fn name(&self) -> &'static str;
+
+    async fn search(&self, args: &QueryArgs) -> Result<SearchPage>;
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    #[default]
+    Google,
+    CustomSearch,
+}
+
+impl BackendKind {
+    /// Name in the config, on the command line and in cache keys.
+    pub fn name(self) -> &'static str {
+        match self {
+            BackendKind::Google => "google",
+            BackendKind::CustomSearch => "custom-search",
+        }
+    }
+
+    /// The backend a [`QueryArgs::cache_key`] was made for.
+    pub fn of_cache_key(key: &str) -> Self {
+        let params = key.split_once(CACHE_KEY_SEPARATOR).map_or("", |(_, p)| p);
+        [BackendKind::CustomSearch]
+            .into_iter()
+            .find(|backend| {
+                params
+                    .split('&')
+                    .any(|part| part.strip_prefix("backend=") == Some(backend.name()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The scraper sends its requests through `proxies`; the API goes out
+    /// directly on the pool's shared client.
+    pub fn create(
+        self,
+        config: &Config,
+        proxies: &Arc<ProxyPool>,
+    ) -> Result<Arc<dyn SearchBackend>> {
+        match self {
+            BackendKind::Google => Ok(Arc::new(GoogleScraper::new(config, proxies.clone()))),
+            BackendKind::CustomSearch => Ok(Arc::new(CustomSearch::from_config(
+                &config.custom_search,
+                config.timeouts.search(),
+                proxies.client(),
+            )?)),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            BackendKind::Google => BackendKind::CustomSearch,
+            BackendKind::CustomSearch => BackendKind::Google,
+        }
+    }
+}
+
+/// A failed attempt that `search_query` is about to retry.
+#[derive(Debug, Clone)]
+pub struct Retrying {
+    /// Number of the attempt about to be made, from 2.
+    pub attempt: u32,
+    /// Why the previous attempt failed.
+    pub reason: String,
+}
+
+/// Runs the search, backing off and retrying after timeouts and 5xx answers
+/// and while Google blocks it, then trying the fallback backend if every
+/// attempt was blocked. Each retry after a block goes through the next proxy
+/// as the blocked one is quarantined. `on_retry` is called before each wait.
+pub async fn search_query(
+    backend: &dyn SearchBackend,
+    args: &QueryArgs,
+    policy: &RetryPolicy,
+    on_retry: &(dyn Fn(Retrying) + Send + Sync),
+) -> Result<SearchPage, SearchError> {
+    let mut attempt = 1;
+    let mut blocks = 0;
+    let mut failures = 0;
+    let blocked = loop {
+        let err = match backend.search(args).await {
+            Ok(page) => return Ok(page),
+            Err(err) => SearchError::from(err),
+        };
+        let delay = match &err {
+            SearchError::Blocked { blocked, .. } => {
+                if blocks == policy.block_retries {
+                    break Blocked {
+                        attempts: attempt,
+                        ..blocked.clone()
+                    };
+                }
+                let delay = policy.block_backoff.delay(blocks);
+                blocks += 1;
+                blocked
+                    .retry_after
+                    .map_or(delay, |wait| wait.min(policy.block_backoff.max).max(delay))
+            }
+            err if err.is_transient() && failures < policy.retries => {
+                failures += 1;
+                policy.backoff.delay(failures - 1)
+            }
+            _ => return Err(err),
+        };
+        attempt += 1;
+        on_retry(Retrying {
+            attempt,
+            reason: err.to_string(),
+        });
+        tokio::time::sleep(delay).await;
+    };
+    let fallback = match &policy.fallback {
+        Some(fallback) => match fallback.search(args).await {
+            Ok(page) => return Ok(page),
+            Err(e) => Some((fallback.name(), e.to_string())),
+        },
+        None => None,
+    };
+    Err(SearchError::Blocked { blocked, fallback })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::retry::Backoff;
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// What the scripted backend answers to one search.
+    enum Step {
+        Page(&'static [&'static str]),
+        Timeout,
+        Status(u16),
+        Block {
+            captcha: bool,
+            retry_after: Option<Duration>,
+        },
+    }
+
+    /// A backend that answers searches from a script, one step per search,
+    /// and counts them.
+    struct Scripted {
+        name: &'static str,
+        steps: Mutex<VecDeque<Step>>,
+        searches: Mutex<u32>,
+    }
+
+    impl Scripted {
+        fn new(name: &'static str, steps: Vec<Step>) -> Self {
+            Self {
+                name,
+                steps: Mutex::new(steps.into()),
+                searches: Mutex::new(0),
+            }
+        }
+
+        fn searches(&self) -> u32 {
+            *self.searches.lock().unwrap()
+        }
+    }
+
+    /// A real `reqwest` timeout, from a server that never answers.
+    async fn timeout_error() -> color_eyre::Report {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        reqwest::Client::new()
+            .get(url)
+            .timeout(Duration::from_millis(1))
+            .send()
+            .await
+            .unwrap_err()
+            .into()
+    }
+
+    #[async_trait]
+    impl SearchBackend for Scripted {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, _args: &QueryArgs) -> Result<SearchPage> {
+            *self.searches.lock().unwrap() += 1;
+            let step = self.steps.lock().unwrap().pop_front();
+            match step.expect("search past the end of the script") {
+                Step::Page(urls) => Ok(SearchPage {
+                    results: results(urls),
+                    strategy: None,
+                    features: SerpFeatures::default(),
+                }),
+                Step::Timeout => Err(timeout_error().await),
+                Step::Status(status) => Err(HttpError {
+                    status,
+                    body: String::new(),
+                }
+                .into()),
+                Step::Block {
+                    captcha,
+                    retry_after,
+                } => Err(Blocked {
+                    captcha,
+                    retry_after,
+                    attempts: 1,
+                }
+                .into()),
+            }
+        }
+    }
+
+    fn no_delay(retries: u32, block_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Backoff::from_secs(0, 0),
+            block_retries,
+            block_backoff: Backoff::from_secs(0, 0),
+            fallback: None,
+        }
+    }
+
+    fn urls(page: &SearchPage) -> Vec<&str> {
+        page.results.iter().map(|r| r.url.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn searches_through_the_backend() {
+        let backend = Scripted::new("scripted", vec![Step::Page(&["a", "b"])]);
+        let page = search_query(&backend, &QueryArgs::default(), &no_delay(2, 2), &|_| {
+            panic!("nothing to retry")
+        })
+        .await
+        .unwrap();
+        assert_eq!(urls(&page), ["https://a/", "https://b/"]);
+        assert_eq!(backend.searches(), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_errors_that_retrying_cant_fix() {
+        let backend = Scripted::new("scripted", vec![Step::Status(404)]);
+        let err = search_query(&backend, &QueryArgs::default(), &no_delay(2, 2), &|_| {
+            panic!("a 404 isn't retried")
+        })
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, SearchError::Http { status: 404, .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(backend.searches(), 1);
+    }
+
+    fn results(urls: &[&str]) -> Vec<QueryResult> {
+        urls.iter()
+            .map(|url| QueryResult {
+                url: format!("https://{}/", url),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Runs `search_query` on `backend`, returning the attempt numbers it
+    /// announced retries for.
+    async fn run(
+        backend: &Scripted,
+        policy: &RetryPolicy,
+    ) -> (Result<SearchPage, SearchError>, Vec<u32>) {
+        let retries = Mutex::new(Vec::new());
+        let on_retry = |retrying: Retrying| retries.lock().unwrap().push(retrying.attempt);
+        let result = search_query(backend, &QueryArgs::default(), policy, &on_retry).await;
+        (result, retries.into_inner().unwrap())
+    }
+
+    fn block() -> Step {
+        Step::Block {
+            captcha: false,
+            retry_after: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_timeouts_and_server_errors() {
+        let backend = Scripted::new(
+            "scripted",
+            vec![Step::Timeout, Step::Status(503), Step::Page(&["a"])],
+        );
+        let (result, retries) = run(&backend, &no_delay(2, 0)).await;
+        assert_eq!(urls(&result.unwrap()), ["https://a/"]);
+        assert_eq!(retries, [2, 3]);
+        assert_eq!(backend.searches(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_retries() {
+        let backend = Scripted::new(
+            "scripted",
+            vec![Step::Status(500), Step::Timeout, Step::Status(502)],
+        );
+        let (result, retries) = run(&backend, &no_delay(2, 5)).await;
+        let err = result.unwrap_err();
+        assert!(
+            matches!(err, SearchError::Http { status: 502, .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(retries, [2, 3]);
+        assert_eq!(backend.searches(), 3);
+
+        let backend = Scripted::new("scripted", vec![Step::Timeout]);
+        let (result, retries) = run(&backend, &no_delay(0, 5)).await;
+        assert!(matches!(result, Err(SearchError::Timeout)));
+        assert!(retries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_block_retries() {
+        let backend = Scripted::new("scripted", vec![block(), block(), block()]);
+        let (result, retries) = run(&backend, &no_delay(5, 2)).await;
+        match result.unwrap_err() {
+            SearchError::Blocked { blocked, fallback } => {
+                assert_eq!(blocked.attempts, 3);
+                assert!(fallback.is_none());
+            }
+            err => panic!("{:?}", err),
+        }
+        assert_eq!(retries, [2, 3]);
+        assert_eq!(backend.searches(), 3);
+    }
+
+    #[tokio::test]
+    async fn counts_every_attempt_in_a_block() {
+        // a timeout retry and a block retry, then the block that ends it
+        let backend = Scripted::new(
+            "scripted",
+            vec![
+                Step::Timeout,
+                block(),
+                Step::Block {
+                    captcha: true,
+                    retry_after: None,
+                },
+            ],
+        );
+        let (result, _) = run(&backend, &no_delay(1, 1)).await;
+        match result.unwrap_err() {
+            SearchError::Blocked { blocked, .. } => {
+                assert_eq!(blocked.attempts, 3);
+                assert!(blocked.captcha, "the last block is reported");
+            }
+            err => panic!("{:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn clamps_retry_after_to_the_block_backoff() {
+        let backend = Scripted::new(
+            "scripted",
+            vec![
+                Step::Block {
+                    captcha: false,
+                    retry_after: Some(Duration::from_secs(3600)),
+                },
+                Step::Page(&["a"]),
+            ],
+        );
+        let policy = RetryPolicy {
+            block_backoff: Backoff {
+                base: Duration::ZERO,
+                max: Duration::from_millis(20),
+            },
+            ..no_delay(0, 1)
+        };
+        let (result, _) = tokio::time::timeout(Duration::from_secs(5), run(&backend, &policy))
+            .await
+            .expect("waited for the whole Retry-After");
+        assert_eq!(urls(&result.unwrap()), ["https://a/"]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_only_after_blocks() {
+        let fallback = Arc::new(Scripted::new("fallback", vec![Step::Page(&["b"])]));
+        let policy = RetryPolicy {
+            fallback: Some(fallback.clone()),
+            ..no_delay(1, 1)
+        };
+
+        let backend = Scripted::new("scripted", vec![Step::Status(503), Step::Status(503)]);
+        let (result, _) = run(&backend, &policy).await;
+        assert!(matches!(result, Err(SearchError::Http { status: 503, .. })));
+        let backend = Scripted::new("scripted", vec![Step::Status(404)]);
+        assert!(run(&backend, &policy).await.0.is_err());
+        assert_eq!(fallback.searches(), 0);
+
+        let backend = Scripted::new("scripted", vec![block(), block()]);
+        let (result, _) = run(&backend, &policy).await;
+        assert_eq!(urls(&result.unwrap()), ["https://b/"]);
+        assert_eq!(backend.searches(), 2);
+        assert_eq!(fallback.searches(), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_the_fallback_failure_with_the_block() {
+        let fallback = Arc::new(Scripted::new("fallback", vec![Step::Status(403)]));
+        let policy = RetryPolicy {
+            fallback: Some(fallback.clone()),
+            ..no_delay(0, 0)
+        };
+        let backend = Scripted::new("scripted", vec![block()]);
+        match run(&backend, &policy).await.0.unwrap_err() {
+            SearchError::Blocked { blocked, fallback } => {
+                assert_eq!(blocked.attempts, 1);
+                let (name, error) = fallback.unwrap();
+                assert_eq!(name, "fallback");
+                assert!(error.contains("403"), "{}", error);
+            }
+            err => panic!("{:?}", err),
+        }
+    }
+
+    /// Old urls, new urls, (added, removed, moved) and the display.
+    type DiffCase = (
+        &'static [&'static str],
+        &'static [&'static str],
+        (usize, usize, usize),
+        &'static str,
+    );
+
+    #[test]
+    fn results_diff_between() {
+        let cases: &[DiffCase] = &[
+            (&["a", "b", "c"], &["a", "b", "c"], (0, 0, 0), "no changes"),
+            (&[], &[], (0, 0, 0), "no changes"),
+            (&["a", "b", "c"], &["c", "a", "b"], (0, 0, 3), "3 moved"),
+            (&["a", "b", "c"], &["a", "c", "b"], (0, 0, 2), "2 moved"),
+            (&["a", "b"], &["c", "d", "e"], (3, 2, 0), "3 new, 2 gone"),
+            (&[], &["a", "b"], (2, 0, 0), "2 new"),
+            (&["a", "b"], &[], (0, 2, 0), "2 gone"),
+            (
+                &["a", "b", "c"],
+                &["a", "d", "b"],
+                (1, 1, 1),
+                "1 new, 1 gone, 1 moved",
+            ),
+            // a repeated url only counts where it first shows up
+            (&["a", "b", "a"], &["a", "b"], (0, 0, 0), "no changes"),
+            (&["a", "b"], &["a", "a", "b"], (0, 0, 0), "no changes"),
+            (&["a", "a"], &["b", "b"], (1, 1, 0), "1 new, 1 gone"),
+            (&["a", "b", "b"], &["b", "a"], (0, 0, 2), "2 moved"),
+        ];
+        for (old, new, (added, removed, moved), display) in cases {
+            let diff = ResultsDiff::between(&results(old), &results(new));
+            let case = format!("{:?} -> {:?}", old, new);
+            assert_eq!(diff.added.len(), *added, "added in {}", case);
+            assert_eq!(diff.removed, *removed, "removed in {}", case);
+            assert_eq!(diff.moved, *moved, "moved in {}", case);
+            assert_eq!(diff.is_empty(), *display == "no changes", "{}", case);
+            assert_eq!(diff.to_string(), *display, "{}", case);
+        }
+    }
+
+    #[test]
+    fn results_diff_lists_added_urls() {
+        let diff = ResultsDiff::between(&results(&["a", "b"]), &results(&["b", "c", "d"]));
+        let mut added: Vec<&str> = diff.added.iter().map(String::as_str).collect();
+        added.sort();
+        assert_eq!(added, ["https://c/", "https://d/"]);
+    }
+}
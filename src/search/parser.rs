@@ -0,0 +1,543 @@
+use super::features::{parse_features, SNIPPET_CONTAINER};
+use super::{QueryResult, SerpFeatures, Vertical};
+use crate::crawl::check::quick_check;
+use color_eyre::Result;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt};
+
+/// The markup variants Google serves, tried in order until one yields results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseStrategy {
+    /// The regular JavaScript enabled desktop page.
+    ModernDesktop,
+    /// The no-JavaScript page served for `gbv=1` or unknown browsers.
+    BasicHtml,
+    /// The layout served to phones.
+    Mobile,
+    /// Last resort: any `/url?q=` redirect link with a heading in it.
+    RedirectLinks,
+    /// `tbm=nws` article cards.
+    News,
+    /// `tbm=isch` image tiles, linking to the page holding the image.
+    Images,
+    /// `tbm=vid` video results.
+    Videos,
+    /// `tbm=bks` book results.
+    Books,
+}
+
+impl ParseStrategy {
+    /// Layouts of the regular web results, in the order they're tried.
+    pub const WEB: [ParseStrategy; 4] = [
+        ParseStrategy::ModernDesktop,
+        ParseStrategy::BasicHtml,
+        ParseStrategy::Mobile,
+        ParseStrategy::RedirectLinks,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ParseStrategy::ModernDesktop => "modern desktop",
+            ParseStrategy::BasicHtml => "basic html",
+            ParseStrategy::Mobile => "mobile",
+            ParseStrategy::RedirectLinks => "redirect links",
+            ParseStrategy::News => "news",
+            ParseStrategy::Images => "images",
+            ParseStrategy::Videos => "videos",
+            ParseStrategy::Books => "books",
+        }
+    }
+
+    /// The dedicated layout of a vertical, if it has one.
+    pub fn for_vertical(vertical: Vertical) -> Option<Self> {
+        match vertical {
+            Vertical::Web => None,
+            Vertical::News => Some(ParseStrategy::News),
+            Vertical::Images => Some(ParseStrategy::Images),
+            Vertical::Videos => Some(ParseStrategy::Videos),
+            Vertical::Books => Some(ParseStrategy::Books),
+        }
+    }
+
+    fn selectors(self) -> Selectors {
+        match self {
+            ParseStrategy::ModernDesktop => Selectors {
+                container: "div.MjjYud, div.g",
+                link: r#"a[jsname="UWckNb"], a:has(h3)"#,
+                title: "h3",
+                // the title block also carries `kb0PBd A9Y9g`, so match the class exactly
+                description: r#"div[class="kb0PBd A9Y9g"], div.VwiC3b, div[data-sncf]"#,
+                display_url: "cite",
+                site_name: "span.VuuXrf",
+                ..Selectors::NONE
+            },
+            ParseStrategy::BasicHtml => Selectors {
+                container: "div.ezO2md, div.Gx5Zad",
+                link: r#"a.fuLhoc, a[href^="/url?q="]"#,
+                title: "span.CVA68e, h3 div.BNeawe, h3",
+                description: "span.FrIlee, div.BNeawe.s3v9rd.AP7Wnd",
+                display_url: "span.dXDvrc, div.BNeawe.UPmit.AP7Wnd",
+                site_name: "",
+                ..Selectors::NONE
+            },
+            ParseStrategy::Mobile => Selectors {
+                container: "div.mnr-c, div.xpd",
+                link: r#"a[href]:has(div[role="heading"]), a[href]:has(h3)"#,
+                title: r#"div[role="heading"], h3"#,
+                description: "div.yDYNvb, div.VwiC3b",
+                display_url: "span.qzEoUe, cite",
+                site_name: "span.VuuXrf, div.CA5RN > div",
+                ..Selectors::NONE
+            },
+            ParseStrategy::RedirectLinks => Selectors {
+                container: "body",
+                link: r#"a[href^="/url?"]:has(h3), a[href^="/url?"]:has(div[role="heading"])"#,
+                title: r#"h3, div[role="heading"]"#,
+                description: "",
+                display_url: "",
+                site_name: "",
+                ..Selectors::NONE
+            },
+            ParseStrategy::News => Selectors {
+                container: "div.SoaBEf, div.JJZKK",
+                link: r#"a.WlydOe, a[href]:has(div[role="heading"])"#,
+                title: r#"div[role="heading"], div.n0jPhd"#,
+                description: "div.GI74Re, div.UqSP2b",
+                source: "div.MgUUmf span, div.CEMjEf span",
+                published: "div.OSrXXb span, span.r0bn4c, div.rbYSKb span",
+                thumbnail: "div.uhHOwf img",
+                ..Selectors::NONE
+            },
+            ParseStrategy::Images => Selectors {
+                container: "div.isv-r, div.eA0Zlc",
+                link: "a.VFACy, a.EZAeBe",
+                title: "div.toI8Rb, h3",
+                source: "div.LAA3yd, span.LAA3yd, div.guK3rf span",
+                thumbnail: "img.YQ4gaf, img.rg_i",
+                ..Selectors::NONE
+            },
+            ParseStrategy::Videos => Selectors {
+                container: "div.RzdJxc, video-voyager",
+                link: "a:has(h3)",
+                title: "h3",
+                description: "div.ITZIwc, div.VwiC3b",
+                display_url: "cite",
+                site_name: "span.VuuXrf",
+                source: "span.pcJO7e",
+                published: "div.gqF9jc > span:last-child",
+                thumbnail: "img.LDPqtd",
+                duration: "div.J1mWY, span.k1U36b, div.c8rnLc span",
+            },
+            ParseStrategy::Books => Selectors {
+                container: "div.Yr5TG",
+                link: "a:has(h3)",
+                title: "h3",
+                description: "span.cmlJmd, div.VwiC3b",
+                display_url: "cite",
+                // "Author · Year · Preview" under the title
+                source: "div.N96wpd > span:first-child",
+                published: "div.N96wpd > span:nth-child(2)",
+                thumbnail: "img.mQVbDb",
+                ..Selectors::NONE
+            },
+        }
+    }
+}
+
+impl fmt::Display for ParseStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Empty selectors mean the layout doesn't show that field.
+struct Selectors {
+    container: &'static str,
+    link: &'static str,
+    title: &'static str,
+    description: &'static str,
+    display_url: &'static str,
+    site_name: &'static str,
+    source: &'static str,
+    published: &'static str,
+    thumbnail: &'static str,
+    duration: &'static str,
+}
+
+impl Selectors {
+    const NONE: Selectors = Selectors {
+        container: "",
+        link: "",
+        title: "",
+        description: "",
+        display_url: "",
+        site_name: "",
+        source: "",
+        published: "",
+        thumbnail: "",
+        duration: "",
+    };
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    /// None of the strategies recognised the page.
+    LayoutNotRecognized,
+    Selector(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::LayoutNotRecognized => write!(
+                f,
+                "Google returned a page layout that isn't recognized. Enable debug mode to save it to debug_raw.html"
+            ),
+            ParseError::Selector(e) => write!(f, "Selector parse error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug)]
+pub struct ParsedPage {
+    pub strategy: ParseStrategy,
+    pub results: Vec<QueryResult>,
+    pub features: SerpFeatures,
+}
+
+/// Elements present on every results page, including ones without results.
+const RESULTS_PAGE_MARKERS: &str = "#search, #rso, #topstuff, #main, #res, #botstuff";
+
+/// Tries the layout of `vertical`, then every web [`ParseStrategy`] in turn;
+/// the no-JavaScript vertical pages use the web layouts. A page that looks
+/// like a results page but has no results parses to an empty list; anything
+/// else is [`ParseError::LayoutNotRecognized`]. Answer boxes are parsed
+/// separately and don't depend on the strategy.
+pub fn parse_search_results(html: &str, vertical: Vertical) -> Result<ParsedPage> {
+    let strategies: Vec<ParseStrategy> = ParseStrategy::for_vertical(vertical)
+        .into_iter()
+        .chain(ParseStrategy::WEB)
+        .collect();
+    parse_with_strategies(html, &strategies)
+}
+
+fn parse_with_strategies(html: &str, strategies: &[ParseStrategy]) -> Result<ParsedPage> {
+    let doc = Html::parse_document(html);
+    let features = parse_features(&doc);
+
+    let mut recognized = None;
+    for &strategy in strategies {
+        let (matched, results) = parse_with(&doc, strategy)?;
+        if !results.is_empty() {
+            return Ok(ParsedPage {
+                strategy,
+                results,
+                features,
+            });
+        }
+        if matched && recognized.is_none() && strategy != ParseStrategy::RedirectLinks {
+            recognized = Some(strategy);
+        }
+    }
+
+    // a page with only an answer box (e.g. a calculation) is still a results page
+    if recognized.is_none() && !features.is_empty() {
+        recognized = Some(ParseStrategy::ModernDesktop);
+    }
+    if recognized.is_none()
+        && doc
+            .select(&parse_selector(RESULTS_PAGE_MARKERS)?)
+            .next()
+            .is_some()
+    {
+        recognized = Some(ParseStrategy::ModernDesktop);
+    }
+    match recognized {
+        Some(strategy) => Ok(ParsedPage {
+            strategy,
+            results: Vec::new(),
+            features,
+        }),
+        None => Err(ParseError::LayoutNotRecognized.into()),
+    }
+}
+
+/// Returns whether any container matched, and the results found in them.
+fn parse_with(doc: &Html, strategy: ParseStrategy) -> Result<(bool, Vec<QueryResult>)> {
+    let selectors = strategy.selectors();
+    let container_sel = parse_selector(selectors.container)?;
+    let link_sel = parse_selector(selectors.link)?;
+    let title_sel = parse_selector(selectors.title)?;
+    let desc_sel = parse_optional_selector(selectors.description)?;
+    let cite_sel = parse_optional_selector(selectors.display_url)?;
+    let site_sel = parse_optional_selector(selectors.site_name)?;
+    let source_sel = parse_optional_selector(selectors.source)?;
+    let published_sel = parse_optional_selector(selectors.published)?;
+    let thumbnail_sel = parse_optional_selector(selectors.thumbnail)?;
+    let duration_sel = parse_optional_selector(selectors.duration)?;
+    let snippet_sel = parse_selector(SNIPPET_CONTAINER)?;
+    let optional_text = |container: &ElementRef, sel: &Option<Selector>| {
+        sel.as_ref()
+            .map(|sel| first_text(container, sel))
+            .unwrap_or_default()
+    };
+
+    let mut matched = false;
+    let mut seen = HashSet::new();
+    let mut results = Vec::new();
+    for container in doc.select(&container_sel) {
+        matched = true;
+        for link in container.select(&link_sel) {
+            // the featured snippet's source, shown again as a result anyway
+            if is_inside(&link, &snippet_sel) {
+                continue;
+            }
+            let Some(url) = link.value().attr("href").and_then(resolve_href) else {
+                continue;
+            };
+            if !seen.insert(url.clone()) {
+                continue;
+            }
+            let title = first_text(&link, &title_sel);
+            let description = match &desc_sel {
+                Some(sel) => first_text(&container, sel),
+                None if strategy == ParseStrategy::RedirectLinks => surrounding_text(&link, &title),
+                None => String::new(),
+            };
+            if title.is_empty() && description.is_empty() {
+                continue;
+            }
+            results.push(
+                QueryResult {
+                    url_supported_flag: quick_check(&url),
+                    title,
+                    display_url: optional_text(&container, &cite_sel),
+                    site_name: optional_text(&container, &site_sel),
+                    source: optional_text(&container, &source_sel),
+                    published: optional_text(&container, &published_sel),
+                    thumbnail: thumbnail_sel
+                        .as_ref()
+                        .and_then(|sel| thumbnail_url(&container, sel))
+                        .unwrap_or_default(),
+                    duration: optional_text(&container, &duration_sel),
+                    url,
+                    description,
+                }
+                .with_fallbacks(),
+            );
+            // organic containers hold one result, sitelinks come after it
+            if strategy != ParseStrategy::RedirectLinks {
+                break;
+            }
+        }
+    }
+    Ok((matched, results))
+}
+
+/// Turns a result href into the target url, unwrapping `/url?q=` redirects
+/// and dropping links back into Google itself.
+fn resolve_href(href: &str) -> Option<String> {
+    let url = if href.starts_with("/url?") {
+        let redirect = Url::parse("https://www.google.com").ok()?.join(href).ok()?;
+        redirect
+            .query_pairs()
+            .find(|(k, _)| k == "q" || k == "url")
+            .map(|(_, v)| v.into_owned())?
+    } else {
+        href.to_string()
+    };
+
+    let parsed = Url::parse(&url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?;
+    let is_google = host == "google.com" || host.ends_with(".google.com");
+    if is_google && (parsed.path().starts_with("/search") || host.starts_with("accounts.")) {
+        return None;
+    }
+    Some(url)
+}
+
+/// Whitespace-normalised text of the first element matching `sel`.
+fn is_inside(element: &ElementRef, sel: &Selector) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| sel.matches(&ancestor))
+}
+
+fn first_text(element: &ElementRef, sel: &Selector) -> String {
+    element
+        .select(sel)
+        .next()
+        .map(|e| normalize(&e.text().collect::<String>()))
+        .unwrap_or_default()
+}
+
+/// Source of the first matching image. Inline `data:` placeholders are
+/// skipped in favour of the lazily loaded `data-src`.
+fn thumbnail_url(element: &ElementRef, sel: &Selector) -> Option<String> {
+    element.select(sel).find_map(|img| {
+        ["data-src", "src"]
+            .iter()
+            .filter_map(|attr| img.value().attr(attr))
+            .find(|src| src.starts_with("http"))
+            .map(str::to_string)
+    })
+}
+
+/// Text of the nearest ancestor that says more than the link itself, used as
+/// the description when a layout has no dedicated snippet element.
+fn surrounding_text(link: &ElementRef, title: &str) -> String {
+    let link_text = normalize(&link.text().collect::<String>());
+    for ancestor in link.ancestors().filter_map(ElementRef::wrap).take(4) {
+        let text = normalize(&ancestor.text().collect::<String>());
+        if text.len() > link_text.len() + 20 {
+            return normalize(&text.replacen(&link_text, "", 1).replacen(title, "", 1));
+        }
+    }
+    String::new()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_optional_selector(selector: &str) -> Result<Option<Selector>> {
+    if selector.is_empty() {
+        return Ok(None);
+    }
+    parse_selector(selector).map(Some)
+}
+
+fn parse_selector(selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|e| ParseError::Selector(format!("{:?}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    /// What a fixture is expected to parse into. Pages that should fail carry
+    /// `error` instead of a strategy.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Expected {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strategy: Option<ParseStrategy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(default)]
+        results: Vec<QueryResult>,
+        #[serde(default, skip_serializing_if = "SerpFeatures::is_empty")]
+        features: SerpFeatures,
+    }
+
+    /// Pages of a vertical are named after it, e.g. `news.html` or
+    /// `videos_mobile.html`; anything else is a web page.
+    fn vertical_of(name: &str) -> Vertical {
+        let prefix = name.split(['_', '.']).next().unwrap_or_default();
+        Vertical::ALL
+            .into_iter()
+            .find(|v| v.name().eq_ignore_ascii_case(prefix))
+            .unwrap_or_default()
+    }
+
+    fn parse_fixture(html: &str, vertical: Vertical) -> Expected {
+        match parse_search_results(html, vertical) {
+            Ok(page) => Expected {
+                strategy: Some(page.strategy),
+                error: None,
+                results: page.results,
+                features: page.features,
+            },
+            Err(e) => Expected {
+                strategy: None,
+                error: Some(match e.downcast_ref::<ParseError>() {
+                    Some(ParseError::LayoutNotRecognized) => "layout_not_recognized".to_string(),
+                    _ => e.to_string(),
+                }),
+                results: Vec::new(),
+                features: SerpFeatures::default(),
+            },
+        }
+    }
+
+    /// Runs the parser over every `tests/fixtures/*.html` page and compares
+    /// the outcome with the `.json` file next to it. Set `UPDATE_FIXTURES=1`
+    /// to (re)write the expectations instead.
+    #[test]
+    fn fixtures_parse_as_expected() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+        let mut html_files: Vec<_> = fs::read_dir(&dir)
+            .expect("tests/fixtures is missing")
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+            .collect();
+        html_files.sort();
+        assert!(!html_files.is_empty(), "no fixtures in {}", dir.display());
+
+        let mut failures = Vec::new();
+        for html_path in html_files {
+            let name = html_path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            let html = fs::read_to_string(&html_path).unwrap();
+            let actual = parse_fixture(&html, vertical_of(&name));
+            let json_path = html_path.with_extension("json");
+
+            if update {
+                let json = serde_json::to_string_pretty(&actual).unwrap();
+                fs::write(&json_path, json + "\n").unwrap();
+                continue;
+            }
+
+            let Ok(expected) = fs::read_to_string(&json_path) else {
+                failures.push(format!("{}: missing {}", name, json_path.display()));
+                continue;
+            };
+            let expected: Expected = serde_json::from_str(&expected)
+                .unwrap_or_else(|e| panic!("{}: invalid expectation: {}", name, e));
+
+            if actual.strategy != expected.strategy || actual.error != expected.error {
+                failures.push(format!(
+                    "{}: expected {:?}/{:?}, got {:?}/{:?}",
+                    name, expected.strategy, expected.error, actual.strategy, actual.error
+                ));
+            }
+            if actual.results.len() != expected.results.len() {
+                failures.push(format!(
+                    "{}: expected {} results, got {}",
+                    name,
+                    expected.results.len(),
+                    actual.results.len()
+                ));
+            }
+            for (i, (a, e)) in actual.results.iter().zip(&expected.results).enumerate() {
+                if a != e {
+                    failures.push(format!(
+                        "{}: result {} differs\n  expected: {:?}\n  actual:   {:?}",
+                        name, i, e, a
+                    ));
+                }
+            }
+            if actual.features != expected.features {
+                failures.push(format!(
+                    "{}: features differ\n  expected: {:?}\n  actual:   {:?}",
+                    name, expected.features, actual.features
+                ));
+            }
+        }
+
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
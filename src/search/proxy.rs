@@ -0,0 +1,401 @@
+use super::{Blocked, HttpError};
+use crate::config::Config;
+use crate::data::cookies::CookieJar;
+use color_eyre::{Report, Result};
+use serde_derive::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How the next proxy is picked among the healthy ones.
+#[derive(PartialEq, Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxySelection {
+    #[default]
+    RoundRobin,
+    /// The proxy with the fewest failures so far, ties broken by use.
+    LeastFailures,
+}
+
+/// What a request through a proxy ended with.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ProxyOutcome {
+    Success,
+    /// Any other error, counted against the proxy but not quarantined.
+    Failure,
+    /// Google answered with 429 or a CAPTCHA page.
+    Blocked,
+    Timeout,
+    /// The proxy itself couldn't be reached.
+    Unreachable,
+}
+
+impl ProxyOutcome {
+    /// Sorts the result of a scraped request. Errors that say nothing about
+    /// the proxy, such as I/O, are `None`.
+    pub fn of(result: &Result<String>) -> Option<Self> {
+        let err = match result {
+            Ok(_) => return Some(ProxyOutcome::Success),
+            Err(err) => err,
+        };
+        Self::of_error(err)
+    }
+
+    fn of_error(err: &Report) -> Option<Self> {
+        for cause in err.chain() {
+            if cause.downcast_ref::<Blocked>().is_some() {
+                return Some(ProxyOutcome::Blocked);
+            }
+            if cause.downcast_ref::<HttpError>().is_some() {
+                return Some(ProxyOutcome::Failure);
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return Some(if e.is_timeout() {
+                    ProxyOutcome::Timeout
+                } else if e.is_connect() {
+                    ProxyOutcome::Unreachable
+                } else {
+                    ProxyOutcome::Failure
+                });
+            }
+        }
+        None
+    }
+
+    fn quarantine_reason(self) -> Option<&'static str> {
+        match self {
+            ProxyOutcome::Blocked => Some("blocked by Google"),
+            ProxyOutcome::Timeout => Some("timed out"),
+            ProxyOutcome::Unreachable => Some("unreachable"),
+            ProxyOutcome::Success | ProxyOutcome::Failure => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProxyHealth {
+    successes: u32,
+    failures: u32,
+    quarantined_until: Option<Instant>,
+    last_error: Option<&'static str>,
+}
+
+impl ProxyHealth {
+    fn is_available(&self, now: Instant) -> bool {
+        self.quarantined_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// Snapshot of one proxy for the status view.
+#[derive(Debug, Clone)]
+pub struct ProxyStatus {
+    pub url: String,
+    pub successes: u32,
+    pub failures: u32,
+    /// Time left in quarantine, `None` when the proxy is in rotation.
+    pub quarantined_for: Option<Duration>,
+    pub last_error: Option<&'static str>,
+}
+
+impl ProxyStatus {
+    /// Share of successful requests, `None` before the first request.
+    pub fn success_rate(&self) -> Option<f64> {
+        let total = self.successes + self.failures;
+        (total > 0).then(|| self.successes as f64 / total as f64)
+    }
+}
+
+/// The proxy a request goes through, handed back with its outcome.
+pub struct ProxyLease {
+    index: Option<usize>,
+    pub client: reqwest::Client,
+}
+
+/// Proxies from the config with their health. Requests go out directly when
+/// none are configured. Every client shares the cookie jar and keeps its
+/// connections alive between searches.
+pub struct ProxyPool {
+    urls: Vec<String>,
+    clients: Vec<reqwest::Client>,
+    direct: reqwest::Client,
+    cookies: Arc<CookieJar>,
+    selection: ProxySelection,
+    quarantine: Duration,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    health: Vec<ProxyHealth>,
+    next: usize,
+}
+
+impl ProxyPool {
+    pub fn new(config: &Config, cookies: Arc<CookieJar>) -> Result<Self> {
+        let builder = || reqwest::Client::builder().cookie_provider(cookies.provider());
+        let clients = config
+            .proxies
+            .iter()
+            .map(|url| Ok(builder().proxy(reqwest::Proxy::all(url)?).build()?))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            urls: config.proxies.clone(),
+            direct: builder().build()?,
+            selection: config.proxy_selection,
+            quarantine: Duration::from_secs(config.proxy_quarantine_secs),
+            state: Mutex::new(PoolState {
+                health: vec![ProxyHealth::default(); clients.len()],
+                next: 0,
+            }),
+            clients,
+            cookies,
+        })
+    }
+
+    /// The client for requests that don't go through a proxy, such as
+    /// fetching pages for the reader.
+    pub fn client(&self) -> reqwest::Client {
+        self.direct.clone()
+    }
+
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Picks the proxy for the next request. When every proxy is
+    /// quarantined the one released soonest is used rather than going out
+    /// directly.
+    pub fn acquire(&self) -> ProxyLease {
+        if self.is_empty() {
+            return ProxyLease {
+                index: None,
+                client: self.direct.clone(),
+            };
+        }
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let len = self.urls.len();
+        let available: Vec<usize> = (0..len)
+            .map(|offset| (state.next + offset) % len)
+            .filter(|&i| state.health[i].is_available(now))
+            .collect();
+        let index = match self.selection {
+            ProxySelection::RoundRobin => available.first().copied(),
+            ProxySelection::LeastFailures => available.iter().copied().min_by_key(|&i| {
+                let health = &state.health[i];
+                (health.failures, health.successes + health.failures)
+            }),
+        }
+        .unwrap_or_else(|| {
+            (0..len)
+                .min_by_key(|&i| state.health[i].quarantined_until)
+                .unwrap_or(0)
+        });
+        state.next = (index + 1) % len;
+        ProxyLease {
+            index: Some(index),
+            client: self.clients[index].clone(),
+        }
+    }
+
+    /// Records how a request through `lease` went, quarantining the proxy
+    /// when it was blocked, timed out or couldn't be reached.
+    pub fn report(&self, lease: &ProxyLease, outcome: ProxyOutcome) {
+        let Some(index) = lease.index else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let health = &mut state.health[index];
+        if outcome == ProxyOutcome::Success {
+            health.successes += 1;
+            return;
+        }
+        health.failures += 1;
+        if let Some(reason) = outcome.quarantine_reason() {
+            health.last_error = Some(reason);
+            if !self.quarantine.is_zero() {
+                health.quarantined_until = Some(Instant::now() + self.quarantine);
+            }
+        } else {
+            health.last_error = Some("request failed");
+        }
+    }
+
+    pub fn status(&self) -> Vec<ProxyStatus> {
+        let state = self.state.lock().unwrap();
+        let now = Instant::now();
+        self.urls
+            .iter()
+            .zip(&state.health)
+            .map(|(url, health)| ProxyStatus {
+                url: url.clone(),
+                successes: health.successes,
+                failures: health.failures,
+                quarantined_for: health
+                    .quarantined_until
+                    .filter(|until| *until > now)
+                    .map(|until| until - now),
+                last_error: health.last_error,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn pool(proxies: &[&str], selection: ProxySelection) -> ProxyPool {
+        let config = Config {
+            proxies: proxies.iter().map(|url| url.to_string()).collect(),
+            proxy_selection: selection,
+            cache_dir: Some(
+                std::env::temp_dir().join(format!("cmdgoogle-proxy-{}", std::process::id())),
+            ),
+            ..Default::default()
+        };
+        let cookies = Arc::new(CookieJar::load(&config).unwrap());
+        ProxyPool::new(&config, cookies).unwrap()
+    }
+
+    const PROXIES: [&str; 3] = [
+        "http://127.0.0.1:9001",
+        "http://127.0.0.1:9002",
+        "http://127.0.0.1:9003",
+    ];
+
+    /// Acquires `n` leases, reporting `outcome` for each, and returns the
+    /// proxies picked.
+    fn picks(pool: &ProxyPool, n: usize, outcome: ProxyOutcome) -> Vec<Option<usize>> {
+        (0..n)
+            .map(|_| {
+                let lease = pool.acquire();
+                pool.report(&lease, outcome);
+                lease.index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn goes_direct_without_proxies() {
+        let pool = pool(&[], ProxySelection::RoundRobin);
+        assert_eq!(picks(&pool, 2, ProxyOutcome::Blocked), [None, None]);
+        assert!(pool.status().is_empty());
+    }
+
+    #[test]
+    fn round_robin_takes_turns_and_skips_quarantined() {
+        let pool = pool(&PROXIES, ProxySelection::RoundRobin);
+        assert_eq!(
+            picks(&pool, 4, ProxyOutcome::Success),
+            [Some(0), Some(1), Some(2), Some(0)]
+        );
+        // 1 is blocked and left out from then on
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Blocked), [Some(1)]);
+        assert_eq!(
+            picks(&pool, 3, ProxyOutcome::Success),
+            [Some(2), Some(0), Some(2)]
+        );
+        let status = pool.status();
+        assert!(status[1].quarantined_for.is_some());
+        assert_eq!(status[1].last_error, Some("blocked by Google"));
+        assert_eq!(status[0].success_rate(), Some(1.0));
+        assert_eq!(status[1].success_rate(), Some(0.5));
+    }
+
+    #[test]
+    fn least_failures_breaks_ties_by_use() {
+        let pool = pool(&PROXIES, ProxySelection::LeastFailures);
+        // plain failures count against a proxy without quarantining it
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Failure), [Some(0)]);
+        assert_eq!(
+            picks(&pool, 4, ProxyOutcome::Success),
+            [Some(1), Some(2), Some(1), Some(2)]
+        );
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Failure), [Some(1)]);
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Failure), [Some(2)]);
+        // all have one failure now, and 0 was used the least
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Success), [Some(0)]);
+        assert!(pool.status().iter().all(|s| s.quarantined_for.is_none()));
+    }
+
+    #[test]
+    fn quarantine_expires() {
+        let mut pool = pool(&PROXIES[..2], ProxySelection::RoundRobin);
+        pool.quarantine = Duration::from_millis(50);
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Timeout), [Some(0)]);
+        assert_eq!(picks(&pool, 2, ProxyOutcome::Success), [Some(1), Some(1)]);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(pool.status()[0].quarantined_for, None);
+        assert_eq!(picks(&pool, 2, ProxyOutcome::Success), [Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn uses_the_first_released_when_all_are_quarantined() {
+        let pool = pool(&PROXIES, ProxySelection::LeastFailures);
+        assert_eq!(
+            picks(&pool, 3, ProxyOutcome::Unreachable),
+            [Some(0), Some(1), Some(2)]
+        );
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Blocked), [Some(0)]);
+        // 0 was quarantined again, so 1 is released first
+        assert_eq!(picks(&pool, 1, ProxyOutcome::Success), [Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn sends_requests_through_a_local_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nproxied",
+                )
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        // nothing listens here once the listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let pool = pool(&[&proxy, &unreachable], ProxySelection::RoundRobin);
+
+        for expected in [ProxyOutcome::Success, ProxyOutcome::Unreachable] {
+            let lease = pool.acquire();
+            let result: Result<String> = async {
+                let response = lease
+                    .client
+                    .get("http://example.test/search")
+                    .send()
+                    .await?;
+                Ok(response.text().await?)
+            }
+            .await;
+            let outcome = ProxyOutcome::of(&result).unwrap();
+            assert_eq!(outcome, expected);
+            if outcome == ProxyOutcome::Success {
+                assert_eq!(result.unwrap(), "proxied");
+            }
+            pool.report(&lease, outcome);
+        }
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET http://example.test/search HTTP/1.1\r\n"));
+        let status = pool.status();
+        assert_eq!((status[0].successes, status[0].failures), (1, 0));
+        assert_eq!(status[1].last_error, Some("unreachable"));
+        assert!(status[1].quarantined_for.is_some());
+    }
+}
@@ -0,0 +1,67 @@
+use super::{BackendKind, ProxyPool, SearchBackend};
+use crate::config::Config;
+use rand::{thread_rng, Rng};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Exponential delays between attempts: `base`, `2 * base`, `4 * base`...
+/// capped at `max`, each with up to half of it taken off at random so
+/// retries from several clients don't line up.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    pub fn from_secs(base: u64, max: u64) -> Self {
+        Self {
+            base: Duration::from_secs(base),
+            max: Duration::from_secs(max),
+        }
+    }
+
+    /// Delay before retry number `attempt`, starting at 0.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let full = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max);
+        let jitter = thread_rng().gen_range(0.0..=0.5);
+        full.mul_f64(1.0 - jitter)
+    }
+}
+
+/// How `search_query` retries failed searches.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Further attempts after a timeout or a 5xx.
+    pub retries: u32,
+    pub backoff: Backoff,
+    /// Further attempts after Google blocks the search, each through the
+    /// next proxy.
+    pub block_retries: u32,
+    pub block_backoff: Backoff,
+    /// Backend tried once when every attempt was blocked.
+    pub fallback: Option<Arc<dyn SearchBackend>>,
+}
+
+impl RetryPolicy {
+    /// The policy for searches on `current`. The fallback backend is left
+    /// out when it is the current one or can't be created, e.g. without API
+    /// credentials.
+    pub fn new(config: &Config, current: BackendKind, proxies: &Arc<ProxyPool>) -> Self {
+        let block = &config.block_retry;
+        let fallback = block
+            .fallback_backend
+            .filter(|kind| *kind != current)
+            .and_then(|kind| kind.create(config, proxies).ok());
+        Self {
+            retries: config.retry.retries,
+            backoff: Backoff::from_secs(config.retry.backoff_secs, config.retry.max_backoff_secs),
+            block_retries: block.retries,
+            block_backoff: Backoff::from_secs(block.backoff_secs, block.max_backoff_secs),
+            fallback,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which Google search to run, sent as `tbm=`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, clap::ValueEnum, Serialize, Deserialize)]
+pub enum Vertical {
+    #[default]
+    Web,
+    News,
+    Images,
+    Videos,
+    Books,
+}
+
+impl Vertical {
+    pub const ALL: [Vertical; 5] = [
+        Vertical::Web,
+        Vertical::News,
+        Vertical::Images,
+        Vertical::Videos,
+        Vertical::Books,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Vertical::Web => "Web",
+            Vertical::News => "News",
+            Vertical::Images => "Images",
+            Vertical::Videos => "Videos",
+            Vertical::Books => "Books",
+        }
+    }
+
+    /// Value of Google's `tbm` parameter; web search has none.
+    pub fn tbm(self) -> Option<&'static str> {
+        match self {
+            Vertical::Web => None,
+            Vertical::News => Some("nws"),
+            Vertical::Images => Some("isch"),
+            Vertical::Videos => Some("vid"),
+            Vertical::Books => Some("bks"),
+        }
+    }
+
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|v| *v == self).unwrap_or(0)
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for Vertical {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
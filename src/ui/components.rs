@@ -1,3 +1,8 @@
+use crate::data::cache::CacheEntry;
+use crate::keymap::{Action, Keymap, Mode};
+use crate::search::{
+    error::Remedy, filters::FilterField, proxy::ProxyStatus, SearchError, SearchFilters,
+};
 use ratatui::{
     layout::{Alignment, Rect},
     prelude::Buffer,
@@ -10,6 +15,10 @@ use ratatui::{
 pub enum InputMode {
     Normal,
     Editing,
+    /// The filter panel is open.
+    Filters,
+    /// The cache panel is open.
+    Cache,
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -17,6 +26,7 @@ pub enum DisplayMode {
     Home,
     Searching,
     Ready,
+    Reader,
 }
 
 #[derive(Default, derive_setters::Setters, Clone)]
@@ -91,7 +101,7 @@ impl Widget for Popup<'_> {
     }
 }
 
-pub fn create_warning_popup(input: &str) -> Popup {
+pub fn create_warning_popup(input: &str) -> Popup<'_> {
     Popup::new("Warning", format!("No search result found for '{}'", input)).with_styles(
         Style::new().red(),
         Style::new().red(),
@@ -99,7 +109,7 @@ pub fn create_warning_popup(input: &str) -> Popup {
     )
 }
 
-pub fn create_error_popup(message: &str) -> Popup {
+pub fn create_error_popup(message: &str) -> Popup<'_> {
     Popup::new("Error", message).with_styles(
         Style::new().red(),
         Style::new().red().add_modifier(Modifier::BOLD),
@@ -107,6 +117,27 @@ pub fn create_error_popup(message: &str) -> Popup {
     )
 }
 
+/// A failed search, colored by what can be done about it: yellow when
+/// trying again may work, red when the backend won't answer and blue when
+/// the network is down.
+pub fn create_search_error_popup(err: &SearchError, hint: String) -> Popup<'static> {
+    let color = match err.remedy() {
+        Remedy::Retry => Color::Yellow,
+        Remedy::SwitchBackend => Color::Red,
+        Remedy::CheckConnection => Color::Blue,
+    };
+    let content = Text::from(vec![
+        Line::from(format!("{}.", err.to_string().trim_end_matches('.'))),
+        Line::default(),
+        Line::from(hint).italic(),
+    ]);
+    Popup::new(err.title(), content).with_styles(
+        Style::new().fg(color),
+        Style::new().fg(color).add_modifier(Modifier::BOLD),
+        Style::new().fg(color),
+    )
+}
+
 pub fn create_cache_popup() -> Popup<'static> {
     Popup::new("Cache", "Cached result used!").with_styles(
         Style::new().green(),
@@ -154,6 +185,136 @@ pub fn create_history_popup(
     )
 }
 
+pub fn create_filter_popup(filters: &SearchFilters, selected: usize) -> Popup<'static> {
+    let mut lines: Vec<Line> = FilterField::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let value = filters.value(*field);
+            let line = format!(
+                "{} {:<14} {}",
+                if i == selected { ">" } else { " " },
+                field.label(),
+                if value.is_empty() { "-" } else { &value }
+            );
+            if i == selected {
+                Line::styled(line, Style::new().black().on_light_blue())
+            } else {
+                Line::from(line)
+            }
+        })
+        .collect();
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        "↑/↓ field | type to edit | Space/←/→ change | Del reset",
+        Style::new().dark_gray(),
+    ));
+    lines.push(Line::styled(
+        "Enter apply | Esc cancel",
+        Style::new().dark_gray(),
+    ));
+    Popup::new("Filters", lines).with_styles(
+        Style::new().yellow(),
+        Style::new().yellow().add_modifier(Modifier::BOLD),
+        Style::new().white(),
+    )
+}
+
+pub fn create_proxy_popup(proxies: &[ProxyStatus]) -> Popup<'static> {
+    let mut lines: Vec<Line> = Vec::new();
+    if proxies.is_empty() {
+        lines.push(Line::from(
+            "No proxies configured, searches go out directly.",
+        ));
+    }
+    for proxy in proxies {
+        let rate = match proxy.success_rate() {
+            Some(rate) => format!("{:>3.0}%", rate * 100.0),
+            None => "   -".to_string(),
+        };
+        let (state, style) = match proxy.quarantined_for {
+            Some(left) => (
+                format!("quarantined {}s", left.as_secs()),
+                Style::new().light_red(),
+            ),
+            None => ("in rotation".to_string(), Style::new().light_green()),
+        };
+        let mut spans = vec![
+            Span::styled(format!("{} ", rate), Style::new().bold()),
+            Span::raw(format!(
+                "{} ({} ok, {} failed) ",
+                proxy.url, proxy.successes, proxy.failures
+            )),
+            Span::styled(state, style),
+        ];
+        if let Some(error) = proxy.last_error {
+            spans.push(Span::styled(
+                format!(" last: {}", error),
+                Style::new().dark_gray(),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    Popup::new("Proxies", lines).with_styles(
+        Style::new().cyan(),
+        Style::new().cyan().add_modifier(Modifier::BOLD),
+        Style::new().white(),
+    )
+}
+
+/// The cached pages with their age, result count and size, scrolled to keep
+/// the selected one in view. Those being refreshed are marked.
+pub fn create_cache_panel(
+    entries: &[CacheEntry],
+    selected: usize,
+    refreshing: &[String],
+    notice: Option<&str>,
+    now: u64,
+    area_height: usize,
+    help: Vec<(String, &'static str, Action)>,
+) -> Popup<'static> {
+    // borders, padding and the footer
+    let rows = area_height.saturating_sub(8).max(1);
+    let start = selected
+        .saturating_sub(rows / 2)
+        .min(entries.len().saturating_sub(rows));
+    let mut lines: Vec<Line> = Vec::new();
+    if entries.is_empty() {
+        lines.push(Line::from("Nothing is cached."));
+    }
+    for (i, entry) in entries.iter().enumerate().skip(start).take(rows) {
+        let mut line = entry.describe(now);
+        if refreshing.contains(&entry.key) {
+            line.push_str(" refreshing…");
+        }
+        lines.push(if i == selected {
+            Line::styled(line, Style::new().black().on_light_green())
+        } else if entry.expired {
+            Line::styled(line, Style::new().dark_gray())
+        } else {
+            Line::from(line)
+        });
+    }
+    lines.push(Line::default());
+    if let Some(notice) = notice {
+        lines.push(Line::styled(notice.to_string(), Style::new().italic()));
+    }
+    let help: Vec<String> = help
+        .into_iter()
+        .map(|(keys, label, _)| format!("{} {}", keys, label))
+        .collect();
+    lines.push(Line::styled(help.join(" | "), Style::new().dark_gray()));
+    let title = match entries.len() {
+        1 => "Cache (1 page)".to_string(),
+        count => format!("Cache ({} pages)", count),
+    };
+    Popup::new(title, lines).with_styles(
+        Style::new().green(),
+        Style::new().green().add_modifier(Modifier::BOLD),
+        Style::new().white(),
+    )
+}
+
 pub fn google_logo_small() -> Paragraph<'static> {
     // small color version
     let google_color = Line::from(vec![
@@ -250,93 +411,132 @@ pub fn create_input_box<'a>(
     input_box
 }
 
+/// Footer entries of each mode: actions sharing a slot and their label.
+fn help_entries(mode: Mode) -> &'static [(&'static [Action], &'static str)] {
+    match mode {
+        Mode::Normal => &[
+            (&[Action::Quit], "quit"),
+            (&[Action::Edit], "edit"),
+            (&[Action::Clear], "clear"),
+            (&[Action::Open], "open"),
+            (&[Action::Read], "read"),
+            (&[Action::More], "more"),
+            (&[Action::Retry], "retry"),
+            (&[Action::Questions], "questions"),
+            (&[Action::Filters], "filters"),
+            (&[Action::Proxies], "proxies"),
+            (&[Action::Cache], "cache"),
+            (&[Action::NextVertical], "vertical"),
+            (&[Action::Debug], "debug"),
+            (&[Action::Notifications], "cacheNotif"),
+            (&[Action::Backend], "backend"),
+            (&[Action::Up, Action::Down], "scroll"),
+        ],
+        Mode::Questions => &[
+            (&[Action::Submit], "search"),
+            (&[Action::Questions], "back"),
+            (&[Action::Up, Action::Down], "select"),
+            (&[Action::Quit], "quit"),
+        ],
+        Mode::Reader => &[
+            (&[Action::Close], "back"),
+            (&[Action::Up, Action::Down], "scroll"),
+            (&[Action::PageUp, Action::PageDown], "page"),
+            (&[Action::Top, Action::Bottom], "top/bottom"),
+            (&[Action::Open], "open"),
+        ],
+        Mode::Searching => &[(&[Action::Cancel], "cancel")],
+        Mode::Filters => &[(&[Action::Submit], "apply"), (&[Action::Cancel], "cancel")],
+        Mode::Cache => &[
+            (&[Action::Up, Action::Down], "select"),
+            (&[Action::Refresh], "refresh"),
+            (&[Action::Delete], "delete"),
+            (&[Action::Close], "close"),
+        ],
+        Mode::Editing => &[
+            (&[Action::Submit], "search"),
+            (&[Action::Cancel], "stop"),
+            (&[Action::Up, Action::Down], "history"),
+        ],
+    }
+}
+
+/// `key=label` pairs of `mode` for the keys currently bound, skipping
+/// entries whose actions are all unbound.
+pub fn help_keys(keymap: &Keymap, mode: Mode) -> Vec<(String, &'static str, Action)> {
+    help_entries(mode)
+        .iter()
+        .filter_map(|(actions, label)| {
+            let keys: Vec<String> = actions
+                .iter()
+                .filter_map(|action| keymap.key(mode, *action))
+                .map(|chord| chord.to_string())
+                .collect();
+            (!keys.is_empty()).then(|| (keys.join("/"), *label, actions[0]))
+        })
+        .collect()
+}
+
 pub fn help_msg(
-    input_mode: &InputMode,
+    mode: Mode,
+    keymap: &Keymap,
     debug_mode: bool,
     enable_cache_hit_notification: bool,
-) -> Paragraph {
+    backend_name: &'static str,
+) -> Paragraph<'static> {
     let base_style = Style::default();
     let key_style = Style::default()
         .fg(Color::Yellow)
         .add_modifier(Modifier::BOLD);
     let action_style = Style::default().fg(Color::Cyan);
-    let status_style = Style::default().fg(Color::LightBlue);
+    let status_style = Style::default()
+        .fg(Color::LightBlue)
+        .add_modifier(Modifier::BOLD);
     let separator_style = Style::default().fg(Color::DarkGray);
 
-    let help_text = match input_mode {
-        InputMode::Normal => {
-            let spans = vec![
-                Span::styled(
-                    "[Normal] ",
-                    base_style.fg(Color::Blue).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled("q", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("quit", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("e", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("edit", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("c", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("clear", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("o", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("open", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("d", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("debug", action_style),
-                Span::styled("(", separator_style),
-                Span::styled(
-                    if debug_mode { "ON" } else { "OFF" },
-                    status_style.add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(") | ", separator_style),
-                Span::styled("n", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("cacheNotif", action_style),
-                Span::styled("(", separator_style),
-                Span::styled(
-                    if enable_cache_hit_notification {
-                        "ON"
-                    } else {
-                        "OFF"
-                    },
-                    status_style.add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(") | ", separator_style),
-                Span::styled("↑/↓", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("scroll", action_style),
-            ];
-            Line::from(spans)
+    let (name, color) = match mode {
+        Mode::Normal => ("[Normal] ", Color::Blue),
+        Mode::Questions => ("[Questions] ", Color::LightGreen),
+        Mode::Reader => ("[Reader] ", Color::Green),
+        Mode::Searching => ("[Searching] ", Color::Yellow),
+        Mode::Filters => ("[Filters] ", Color::Yellow),
+        Mode::Cache => ("[Cache] ", Color::Green),
+        Mode::Editing => ("[Editing] ", Color::Magenta),
+    };
+    let mut spans = vec![Span::styled(
+        name,
+        base_style.fg(color).add_modifier(Modifier::BOLD),
+    )];
+    for (i, (keys, label, action)) in help_keys(keymap, mode).into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" | ", separator_style));
         }
-        InputMode::Editing => {
-            let spans = vec![
-                Span::styled(
-                    "[Editing] ",
-                    base_style.fg(Color::Magenta).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled("Enter", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("search", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("Esc", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("stop", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("↑/↓", key_style),
-                Span::styled("=", separator_style),
-                Span::styled("history", action_style),
-                Span::styled(" | ", separator_style),
-                Span::styled("<type>", Style::default().fg(Color::LightGreen)),
-            ];
-            Line::from(spans)
+        spans.push(Span::styled(keys, key_style));
+        spans.push(Span::styled("=", separator_style));
+        spans.push(Span::styled(label, action_style));
+        let status = match (mode, action) {
+            (Mode::Normal, Action::Debug) => Some(if debug_mode { "ON" } else { "OFF" }),
+            (Mode::Normal, Action::Notifications) => Some(if enable_cache_hit_notification {
+                "ON"
+            } else {
+                "OFF"
+            }),
+            (Mode::Normal, Action::Backend) => Some(backend_name),
+            _ => None,
+        };
+        if let Some(status) = status {
+            spans.push(Span::styled("(", separator_style));
+            spans.push(Span::styled(status, status_style));
+            spans.push(Span::styled(")", separator_style));
         }
-    };
+    }
+    if mode == Mode::Editing {
+        spans.push(Span::styled(" | ", separator_style));
+        spans.push(Span::styled(
+            "<type>",
+            Style::default().fg(Color::LightGreen),
+        ));
+    }
 
-    Paragraph::new(help_text)
+    Paragraph::new(Line::from(spans))
 }
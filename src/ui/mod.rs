@@ -1,5 +1,6 @@
 pub mod components;
 pub mod home;
+pub mod reader;
 pub mod ready;
 pub mod searching;
 
@@ -0,0 +1,179 @@
+use super::components::*;
+use crate::app::App;
+use crate::crawl::reader::{Block, Page};
+use crate::keymap::Mode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block as BlockWidget, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+pub fn render(app: &mut App, frame: &mut Frame) {
+    let screen = frame.area();
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(screen);
+    let help = help_msg(
+        Mode::Reader,
+        &app.keymap,
+        app.debug_mode,
+        app.cache.enable_cache_hit_notification,
+        app.backend.name(),
+    );
+
+    let Some(page) = &app.reader else {
+        if let Some(pending) = &app.pending_read {
+            let header = Paragraph::new(vec![
+                Line::styled(
+                    format!("Loading... {}", app.spinner_frames[app.spinner_index]),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::ITALIC),
+                ),
+                Line::styled(pending.url.clone(), Style::default().fg(Color::Cyan)),
+            ]);
+            frame.render_widget(header, layout[0]);
+            frame.render_widget(help, layout[1]);
+            frame.render_widget(
+                BlockWidget::default().borders(Borders::ALL).title("Reader"),
+                layout[2],
+            );
+        }
+        return;
+    };
+
+    let title = if page.title.is_empty() {
+        page.url.clone()
+    } else {
+        page.title.clone()
+    };
+    let header = Paragraph::new(vec![
+        Line::styled(
+            title,
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Line::styled(page.url.clone(), Style::default().fg(Color::Cyan)),
+    ]);
+    frame.render_widget(header, layout[0]);
+    frame.render_widget(help, layout[1]);
+
+    let content_area = layout[2];
+    let content = Paragraph::new(page_lines(page))
+        .wrap(Wrap { trim: false })
+        .block(BlockWidget::default().borders(Borders::ALL).title("Reader"));
+
+    // keep the last screenful of text visible instead of scrolling into nothing
+    let total = content.line_count(content_area.width) as u16;
+    let max_scroll = total.saturating_sub(content_area.height);
+    app.reader_scroll = app.reader_scroll.min(max_scroll);
+
+    frame.render_widget(content.scroll((app.reader_scroll, 0)), content_area);
+}
+
+fn page_lines(page: &Page) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for block in &page.blocks {
+        match block {
+            Block::Heading(level, text) => {
+                if !lines.is_empty() {
+                    lines.push(Line::default());
+                }
+                let color = if *level <= 2 {
+                    Color::LightGreen
+                } else {
+                    Color::Green
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", "#".repeat(*level as usize)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        text.clone(),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                ]));
+                lines.push(Line::default());
+            }
+            Block::Paragraph(text) => {
+                lines.push(Line::from(text.clone()));
+                lines.push(Line::default());
+            }
+            Block::ListItem {
+                depth,
+                marker,
+                text,
+            } => {
+                let bullet = match marker {
+                    Some(n) => format!("{}. ", n),
+                    None => "• ".to_string(),
+                };
+                lines.push(Line::from(vec![
+                    Span::raw("  ".repeat(depth + 1)),
+                    Span::styled(bullet, Style::default().fg(Color::Yellow)),
+                    Span::raw(text.clone()),
+                ]));
+            }
+            Block::Code(code) => {
+                for code_line in code.lines() {
+                    lines.push(Line::from(vec![
+                        Span::styled("│ ", Style::default().fg(Color::DarkGray)),
+                        Span::styled(code_line.to_string(), Style::default().fg(Color::Magenta)),
+                    ]));
+                }
+                lines.push(Line::default());
+            }
+            Block::Quote(text) => {
+                lines.push(Line::styled(
+                    format!("> {}", text),
+                    Style::default()
+                        .fg(Color::Gray)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+                lines.push(Line::default());
+            }
+        }
+    }
+
+    if !page.links.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "Links",
+            Style::default()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        ));
+        for (i, link) in page.links.iter().enumerate() {
+            let mut spans = vec![Span::styled(
+                format!("[{}] ", i + 1),
+                Style::default().fg(Color::Yellow),
+            )];
+            if !link.text.is_empty() {
+                spans.push(Span::raw(format!("{} ", link.text)));
+            }
+            spans.push(Span::styled(
+                link.href.clone(),
+                Style::default().fg(Color::Cyan),
+            ));
+            lines.push(Line::from(spans));
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(Line::styled(
+            "No readable content found on this page.",
+            Style::default().fg(Color::LightRed),
+        ));
+    }
+    lines
+}
@@ -1,11 +1,14 @@
 use super::components::*;
-use crate::app::App;
-use crate::utils::StringExt;
+use crate::app::{App, Revalidation};
+use crate::config::Theme;
+use crate::keymap::{Action, KeyChord, Mode};
+use crate::search::{QueryResult, SerpFeatures, Vertical};
+use crate::utils::format_age;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::{Line, Text},
-    widgets::{Block, Borders, List, ListItem},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap},
     Frame,
 };
 
@@ -41,22 +44,73 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         });
 
     let help_msg = help_msg(
-        &app.input_mode,
+        app.key_mode(),
+        &app.keymap,
         app.debug_mode,
         app.cache.enable_cache_hit_notification,
+        app.backend.name(),
     );
     frame.render_widget(help_msg, second_layout[0]);
 
-    let search_area = second_layout[1];
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(second_layout[1]);
+    let tabs = Tabs::new(Vertical::ALL.map(Vertical::name))
+        .select(app.vertical.index())
+        .style(Style::default().fg(Color::DarkGray))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_widget(tabs, areas[0]);
+
+    let mut search_area = areas[1];
+    if !app.features.is_empty() {
+        let focused = app.questions_focused.then_some(app.question_idx);
+        let panel = Paragraph::new(feature_lines(
+            &app.features,
+            focused,
+            app.keymap.key(Mode::Normal, Action::Questions),
+            &app.config.theme,
+        ))
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Quick answers")
+                .border_style(if app.questions_focused {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                }),
+        );
+        // never let the answers push the results off screen
+        let height = (panel.line_count(search_area.width) as u16).min(search_area.height / 2);
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(height), Constraint::Min(0)])
+            .split(search_area);
+        frame.render_widget(panel, areas[0]);
+        search_area = areas[1];
+    }
+    let theme = &app.config.theme;
+    let selection_style = Style::default()
+        .bg(theme.selection_bg)
+        .fg(theme.selection_fg);
     let items: Vec<ListItem> = app
         .messages
         .iter()
         .enumerate()
         .map(|(i, msg)| {
-            let highlighted_text = highlight_matches(&msg.description, &app.input);
-            let lines = vec![
-                Line::styled(format!("{} ", msg.url), Style::default().fg(Color::Cyan)),
-                highlighted_text.lines[0].clone(),
+            let description = highlight_matches(&msg.description, &app.input, theme.highlight);
+            let crawl_line = if app.is_crawl_check_pending(&msg.url) {
+                Line::styled(
+                    "Crawl supported: checking...",
+                    Style::default().fg(Color::Yellow),
+                )
+            } else {
                 Line::styled(
                     format!("Crawl supported: {}", msg.url_supported_flag),
                     Style::default().fg(if msg.url_supported_flag {
@@ -64,10 +118,35 @@ pub fn render(app: &mut App, frame: &mut Frame) {
                     } else {
                         Color::Red
                     }),
-                ),
+                )
+            };
+            let title_style = Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD);
+            let mut title = vec![Span::styled(msg.title.clone(), title_style)];
+            if let Some(Revalidation::Done(diff)) = app.staleness.as_ref().map(|s| &s.status) {
+                if diff.added.contains(&msg.url) {
+                    title.push(Span::styled(" new", Style::default().fg(Color::Green)));
+                }
+            }
+            let mut lines = vec![
+                Line::from(title),
+                Line::from(vec![
+                    Span::styled(msg.site_name.clone(), Style::default().fg(theme.site)),
+                    Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+                    Span::styled(msg.display_url.clone(), Style::default().fg(theme.url)),
+                ]),
+                description,
             ];
-            let style = if i == app.selected_idx && app.input_mode == InputMode::Normal {
-                Style::default().bg(Color::Blue).fg(Color::White)
+            if let Some(details) = details_line(msg) {
+                lines.push(details);
+            }
+            lines.push(crawl_line);
+            let style = if i == app.selected_idx
+                && app.input_mode == InputMode::Normal
+                && !app.questions_focused
+            {
+                selection_style
             } else {
                 Style::default()
             };
@@ -76,13 +155,38 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         .collect();
 
     let results_title = if !app.messages.is_empty() {
-        format!("Results for '{}'", app.input.clone())
+        let mut title = format!("Results for '{}'", app.input.clone());
+        if app.current_page > 0 {
+            title.push_str(&format!(" - {} pages", app.current_page + 1));
+        }
+        if !app.filters.is_empty() {
+            title.push_str(&format!(" [{}]", app.filters));
+        }
+        if app.is_loading {
+            title.push_str(" (loading more...)");
+        } else if !app.has_more_results {
+            title.push_str(" (no more results)");
+        }
+        if let Some(staleness) = &app.staleness {
+            let age = format_age(staleness.age);
+            title.push_str(&match &staleness.status {
+                Revalidation::Running => format!(" (cached {} ago, refreshing...)", age),
+                Revalidation::Failed(err) => {
+                    format!(" (cached {} ago, refresh failed: {})", age, err)
+                }
+                Revalidation::Done(diff) => format!(" (updated: {})", diff),
+            });
+        }
+        if let (true, Some(strategy)) = (app.debug_mode, app.parse_strategy) {
+            title.push_str(&format!(" [parser: {}]", strategy));
+        }
+        title
     } else {
         "Search results".to_string()
     };
     let search_results_block = List::new(items)
         .block(Block::default().borders(Borders::ALL).title(results_title))
-        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+        .highlight_style(selection_style);
 
     frame.render_stateful_widget(
         search_results_block,
@@ -91,23 +195,204 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     );
 }
 
-pub fn highlight_matches<'a>(text: &'a str, input: &'a str) -> Text<'a> {
-    if input.is_empty() {
-        return Text::from(text.to_string());
+/// Source, date, duration and thumbnail of a vertical result.
+fn details_line(result: &QueryResult) -> Option<Line<'static>> {
+    let fields = [
+        (&result.source, Color::Magenta),
+        (&result.published, Color::Gray),
+        (&result.duration, Color::Yellow),
+        (&result.thumbnail, Color::DarkGray),
+    ];
+    let mut spans = Vec::new();
+    for (value, color) in fields {
+        if value.is_empty() {
+            continue;
+        }
+        if !spans.is_empty() {
+            spans.push(Span::styled(" · ", Style::default().fg(Color::DarkGray)));
+        }
+        spans.push(Span::styled(value.clone(), Style::default().fg(color)));
     }
-    let mut highlighted_text = Text::default();
-    let lower_input = input.to_lowercase();
-    let mut start = 0;
-    let txt_lower = text.to_lowercase();
-    while let Some(pos) = txt_lower[start..].find(&lower_input) {
-        let end = start + pos;
-        highlighted_text.extend(Text::from(text[start..end].to_string()));
-        highlighted_text.extend(Text::styled(
-            &text[end..end + lower_input.grapheme_len()],
-            Style::default().bg(Color::Yellow).fg(Color::Black),
-        ));
-        start = end + lower_input.grapheme_len();
+    (!spans.is_empty()).then(|| Line::from(spans))
+}
+
+/// Featured snippet, knowledge panel and "People also ask" questions, with
+/// the question at `selected` highlighted.
+fn feature_lines(
+    features: &SerpFeatures,
+    selected: Option<usize>,
+    questions_key: Option<KeyChord>,
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    let label_style = Style::default()
+        .fg(Color::LightGreen)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+
+    if let Some(snippet) = &features.featured_snippet {
+        lines.push(Line::styled("Featured snippet", label_style));
+        lines.push(Line::from(snippet.text.clone()));
+        if !snippet.source_url.is_empty() {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    snippet.source_title.clone(),
+                    Style::default().fg(theme.title),
+                ),
+                Span::styled(" · ", Style::default().fg(Color::DarkGray)),
+                Span::styled(snippet.source_url.clone(), Style::default().fg(theme.url)),
+            ]));
+        }
+    }
+
+    if let Some(panel) = &features.knowledge_panel {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        let mut heading = vec![Span::styled(panel.title.clone(), label_style)];
+        if !panel.subtitle.is_empty() {
+            heading.push(Span::styled(
+                format!(" ({})", panel.subtitle),
+                Style::default().fg(Color::Gray),
+            ));
+        }
+        lines.push(Line::from(heading));
+        if !panel.description.is_empty() {
+            lines.push(Line::from(panel.description.clone()));
+        }
+        for (label, value) in &panel.facts {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}: ", label), Style::default().fg(Color::Yellow)),
+                Span::raw(value.clone()),
+            ]));
+        }
+    }
+
+    if !features.people_also_ask.is_empty() {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        let title = match questions_key {
+            Some(key) => format!("People also ask ({})", key),
+            None => "People also ask".to_string(),
+        };
+        lines.push(Line::styled(title, label_style));
+        for (i, question) in features.people_also_ask.iter().enumerate() {
+            let style = if selected == Some(i) {
+                Style::default()
+                    .bg(theme.selection_bg)
+                    .fg(theme.selection_fg)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(format!("? {}", question), style));
+        }
+    }
+    lines
+}
+
+/// `text` with every case-insensitive match of `input` highlighted.
+pub fn highlight_matches<'a>(text: &'a str, input: &str, color: Color) -> Line<'a> {
+    let needle: Vec<char> = input.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Line::from(text);
+    }
+    let style = Style::default().bg(color).fg(Color::Black);
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut spans = Vec::new();
+    let mut plain = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let Some(len) = match_len(&chars[i..], &needle) else {
+            i += 1;
+            continue;
+        };
+        let start = chars[i].0;
+        let end = chars.get(i + len).map_or(text.len(), |(pos, _)| *pos);
+        if plain < start {
+            spans.push(Span::raw(&text[plain..start]));
+        }
+        spans.push(Span::styled(&text[start..end], style));
+        plain = end;
+        i += len;
+    }
+    if plain < text.len() {
+        spans.push(Span::raw(&text[plain..]));
+    }
+    Line::from(spans)
+}
+
+/// How many of `chars` lowercase to exactly `needle`, if they do.
+fn match_len(chars: &[(usize, char)], needle: &[char]) -> Option<usize> {
+    let mut matched = 0;
+    for (count, (_, c)) in chars.iter().enumerate() {
+        for lower in c.to_lowercase() {
+            if needle.get(matched) != Some(&lower) {
+                return None;
+            }
+            matched += 1;
+        }
+        if matched == needle.len() {
+            return Some(count + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(line: &Line) -> Vec<(String, bool)> {
+        line.spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style.bg.is_some()))
+            .collect()
+    }
+
+    #[test]
+    fn highlights_every_match_on_one_line() {
+        let line = highlight_matches("Rust and rust again", "RUST", Color::Yellow);
+        assert_eq!(
+            spans(&line),
+            [
+                ("Rust".to_string(), true),
+                (" and ".to_string(), false),
+                ("rust".to_string(), true),
+                (" again".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn slices_non_ascii_text_at_char_boundaries() {
+        // "İ" lowercases to two chars, "ß" has no single-char uppercase
+        let line = highlight_matches("İstanbul Straße — Ünïcode", "straße — ü", Color::Yellow);
+        assert_eq!(
+            spans(&line),
+            [
+                ("İstanbul ".to_string(), false),
+                ("Straße — Ü".to_string(), true),
+                ("nïcode".to_string(), false),
+            ]
+        );
+        let line = highlight_matches("İstanbul", "i̇s", Color::Yellow);
+        assert_eq!(
+            spans(&line),
+            [("İs".to_string(), true), ("tanbul".to_string(), false)]
+        );
+        let line = highlight_matches("日本語のテキスト", "テキ", Color::Yellow);
+        assert_eq!(spans(&line)[1], ("テキ".to_string(), true));
+    }
+
+    #[test]
+    fn leaves_text_without_matches_alone() {
+        assert_eq!(
+            spans(&highlight_matches("nothing here", "", Color::Yellow)),
+            [("nothing here".to_string(), false)]
+        );
+        assert_eq!(
+            spans(&highlight_matches("ab", "abc", Color::Yellow)),
+            [("ab".to_string(), false)]
+        );
     }
-    highlighted_text.extend(Text::from(text[start..].to_string()));
-    highlighted_text
 }
@@ -1,8 +1,10 @@
 use super::components::*;
 use crate::app::App;
+use crate::keymap::Mode;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
+    text::Line,
     widgets::Paragraph,
     Frame,
 };
@@ -26,12 +28,31 @@ pub fn render(app: &App, frame: &mut Frame) {
         .style(Style::default().add_modifier(Modifier::BOLD));
     frame.render_widget(google_logo, layout[0]);
 
-    let loading_paragraph = Paragraph::new(format!("Searching... {}", spinner))
-        .alignment(Alignment::Center)
-        .style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::ITALIC),
-        );
+    let cancel_hint = help_keys(&app.keymap, Mode::Searching)
+        .into_iter()
+        .map(|(keys, label, _)| format!("{}={}", keys, label))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let mut lines = vec![Line::styled(
+        format!("Searching... {}", spinner),
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::ITALIC),
+    )];
+    if let Some(retry) = app
+        .pending_search
+        .as_ref()
+        .and_then(|p| p.retrying.as_ref())
+    {
+        lines.push(Line::styled(
+            format!("Attempt {} ({})", retry.attempt, retry.reason),
+            Style::default().fg(Color::LightRed),
+        ));
+    }
+    lines.push(Line::styled(
+        cancel_hint,
+        Style::default().fg(Color::DarkGray),
+    ));
+    let loading_paragraph = Paragraph::new(lines).alignment(Alignment::Center);
     frame.render_widget(loading_paragraph, layout[1]);
 }